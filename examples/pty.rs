@@ -4,9 +4,10 @@ use std::{cell::RefCell, convert::Infallible, fs::File, process::Command, time::
 
 use embedded_graphics_core::{pixelcolor::Rgb888, prelude::*};
 use embedded_graphics_simulator::{
+    sdl2::{Keycode, Mod, MouseButton as SdlMouseButton},
     OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
 };
-use embedded_term::Console;
+use embedded_term::{Console, Key, Modifiers, MouseButton, MouseEventKind};
 use libc::{self, winsize};
 use mio::{unix::EventedFd, Events, Poll, PollOpt, Ready, Token};
 use pty::fork::Fork;
@@ -23,6 +24,7 @@ fn main() {
         let display = RefCell::new(display);
 
         let mut console = Console::on_frame_buffer(DisplayWrapper(&display));
+        console.set_deferred(true);
         let poll = Poll::new().unwrap();
         poll.register(
             &EventedFd(&master.as_raw_fd()),
@@ -64,6 +66,8 @@ fn main() {
 
         let output_settings = OutputSettingsBuilder::new().build();
         let mut window = Window::new("Example", &output_settings);
+        let mut mouse_held = None;
+        let mut mouse_cell = None;
 
         loop {
             poll.poll(&mut events, Some(Duration::from_millis(10)))
@@ -74,9 +78,7 @@ fn main() {
                 match event.token() {
                     Token(0) => {
                         let len = master.read(&mut buffer).unwrap();
-                        for c in &buffer[..len] {
-                            console.write_byte(*c);
-                        }
+                        console.write_bytes(&buffer[..len]);
                     }
                     Token(1) => {
                         let len = stdin.read(&mut buffer).unwrap();
@@ -86,13 +88,57 @@ fn main() {
                 }
             }
 
-            while let Some(byte) = console.pop_report() {
-                master.write_all(&[byte]).unwrap();
+            console.flush();
+            window.update(&display.borrow_mut());
+            for event in window.events() {
+                match event {
+                    SimulatorEvent::Quit => return,
+                    SimulatorEvent::KeyDown {
+                        keycode, keymod, ..
+                    } => {
+                        if let Some((key, modifiers)) = translate_key(keycode, keymod) {
+                            console.key_event(key, modifiers);
+                        }
+                    }
+                    SimulatorEvent::MouseButtonDown { mouse_btn, point }
+                    | SimulatorEvent::MouseButtonUp { mouse_btn, point } => {
+                        let button = translate_button(mouse_btn);
+                        let kind = if matches!(event, SimulatorEvent::MouseButtonDown { .. }) {
+                            mouse_held = button;
+                            MouseEventKind::Press
+                        } else {
+                            mouse_held = None;
+                            MouseEventKind::Release
+                        };
+                        if let (Some(button), Some((row, col))) = (button, console.cell_at(point)) {
+                            console.mouse_event(kind, Some(button), row, col, Modifiers::empty());
+                        }
+                    }
+                    SimulatorEvent::MouseMove { point } => {
+                        let cell = console.cell_at(point);
+                        if let Some((row, col)) = cell.filter(|_| cell != mouse_cell) {
+                            let kind = MouseEventKind::Motion;
+                            console.mouse_event(kind, mouse_held, row, col, Modifiers::empty());
+                        }
+                        mouse_cell = cell;
+                    }
+                    SimulatorEvent::MouseWheel { scroll_delta, .. } => {
+                        let button = if scroll_delta.y > 0 {
+                            MouseButton::WheelUp
+                        } else {
+                            MouseButton::WheelDown
+                        };
+                        if let Some((row, col)) = mouse_cell {
+                            let kind = MouseEventKind::Press;
+                            console.mouse_event(kind, Some(button), row, col, Modifiers::empty());
+                        }
+                    }
+                    _ => {}
+                }
             }
 
-            window.update(&display.borrow_mut());
-            if window.events().any(|e| e == SimulatorEvent::Quit) {
-                break;
+            while let Some(byte) = console.pop_report() {
+                master.write_all(&[byte]).unwrap();
             }
         }
     } else {
@@ -106,6 +152,69 @@ fn main() {
     }
 }
 
+/// Translate a key pressed in the window, applying Shift to letters only
+fn translate_key(keycode: Keycode, keymod: Mod) -> Option<(Key, Modifiers)> {
+    let mut modifiers = Modifiers::empty();
+    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+        modifiers |= Modifiers::SHIFT;
+    }
+    if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+        modifiers |= Modifiers::ALT;
+    }
+    if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+        modifiers |= Modifiers::CONTROL;
+    }
+    let key = match keycode {
+        Keycode::Return => Key::Enter,
+        Keycode::Tab => Key::Tab,
+        Keycode::Backspace => Key::Backspace,
+        Keycode::Escape => Key::Escape,
+        Keycode::Up => Key::Up,
+        Keycode::Down => Key::Down,
+        Keycode::Left => Key::Left,
+        Keycode::Right => Key::Right,
+        Keycode::Home => Key::Home,
+        Keycode::End => Key::End,
+        Keycode::Insert => Key::Insert,
+        Keycode::Delete => Key::Delete,
+        Keycode::PageUp => Key::PageUp,
+        Keycode::PageDown => Key::PageDown,
+        Keycode::F1 => Key::F(1),
+        Keycode::F2 => Key::F(2),
+        Keycode::F3 => Key::F(3),
+        Keycode::F4 => Key::F(4),
+        Keycode::F5 => Key::F(5),
+        Keycode::F6 => Key::F(6),
+        Keycode::F7 => Key::F(7),
+        Keycode::F8 => Key::F(8),
+        Keycode::F9 => Key::F(9),
+        Keycode::F10 => Key::F(10),
+        Keycode::F11 => Key::F(11),
+        Keycode::F12 => Key::F(12),
+        Keycode::KpEnter => Key::KeypadEnter,
+        _ => {
+            let c = char::from_u32(keycode as i32 as u32)
+                .filter(|c| *c == ' ' || c.is_ascii_graphic())?;
+            if modifiers.contains(Modifiers::SHIFT) {
+                modifiers.remove(Modifiers::SHIFT);
+                Key::Char(c.to_ascii_uppercase())
+            } else {
+                Key::Char(c)
+            }
+        }
+    };
+    Some((key, modifiers))
+}
+
+fn translate_button(button: SdlMouseButton) -> Option<MouseButton> {
+    match button {
+        SdlMouseButton::Left => Some(MouseButton::Left),
+        SdlMouseButton::Middle => Some(MouseButton::Middle),
+        SdlMouseButton::Right => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
 struct DisplayWrapper<'a>(&'a RefCell<SimulatorDisplay<Rgb888>>);
 
 impl DrawTarget for DisplayWrapper<'_> {
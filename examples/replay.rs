@@ -15,6 +15,7 @@ fn main() {
     let display = SimulatorDisplay::<Rgb888>::new(DISPLAY_SIZE);
     let display = Arc::new(Mutex::new(display));
     let mut console = Console::on_frame_buffer(DisplayWrapper(display.clone()));
+    console.set_deferred(true);
 
     thread::spawn(move || {
         thread::sleep(Duration::from_millis(1000));
@@ -29,6 +30,7 @@ fn main() {
 
         let time = Instant::now();
         console.write_str(&input).unwrap();
+        console.flush();
         println!("Render time: {:?}", time.elapsed());
     });
 
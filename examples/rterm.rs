@@ -18,12 +18,19 @@ fn main() {
 
     let mut console = Console::on_frame_buffer(DisplayWrapper(display.clone()));
     std::thread::spawn(move || {
-        for c in std::io::stdin().lock().bytes() {
-            let c = c.unwrap();
-            if c == 0xff {
+        let mut stdin = std::io::stdin().lock();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let len = stdin.read(&mut buffer).unwrap();
+            if len == 0 {
                 break;
             }
-            console.write_byte(c);
+            let bytes = &buffer[..len];
+            if let Some(end) = bytes.iter().position(|&c| c == 0xff) {
+                console.write_bytes(&bytes[..end]);
+                break;
+            }
+            console.write_bytes(bytes);
         }
     });
 
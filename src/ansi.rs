@@ -58,6 +58,8 @@ pub enum Mode {
     Origin = 6,
     /// ?7
     LineWrap = 7,
+    /// ?9
+    ReportMouseX10 = 9,
     /// ?12
     BlinkingCursor = 12,
     /// 20
@@ -67,6 +69,8 @@ pub enum Mode {
     LineFeedNewLine = 20,
     /// ?25
     ShowCursor = 25,
+    /// ?47
+    SwapScreen = 47,
     /// ?1000
     ReportMouseClicks = 1000,
     /// ?1002
@@ -83,6 +87,8 @@ pub enum Mode {
     AlternateScroll = 1007,
     /// ?1042
     UrgencyHints = 1042,
+    /// ?1047
+    SwapScreenAndClear = 1047,
     /// ?1049
     SwapScreenAndSetRestoreCursor = 1049,
     /// ?2004
@@ -104,8 +110,10 @@ impl Mode {
                 3 => Mode::ColumnMode,
                 6 => Mode::Origin,
                 7 => Mode::LineWrap,
+                9 => Mode::ReportMouseX10,
                 12 => Mode::BlinkingCursor,
                 25 => Mode::ShowCursor,
+                47 => Mode::SwapScreen,
                 1000 => Mode::ReportMouseClicks,
                 1002 => Mode::ReportCellMouseMotion,
                 1003 => Mode::ReportAllMouseMotion,
@@ -114,6 +122,7 @@ impl Mode {
                 1006 => Mode::SgrMouse,
                 1007 => Mode::AlternateScroll,
                 1042 => Mode::UrgencyHints,
+                1047 => Mode::SwapScreenAndClear,
                 1049 => Mode::SwapScreenAndSetRestoreCursor,
                 2004 => Mode::BracketedPaste,
                 _ => {
@@ -131,6 +140,67 @@ impl Mode {
     }
 }
 
+/// Shape of the cursor.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum CursorShape {
+    /// Cursor is a block like `▒`.
+    #[default]
+    Block,
+    /// Cursor is an underscore like `_`.
+    Underline,
+    /// Cursor is a vertical bar `⎸`.
+    Bar,
+}
+
+/// Style of the cursor, as selected by DECSCUSR.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum CursorStyle {
+    /// Blinking block.
+    BlinkingBlock,
+    /// Steady block.
+    #[default]
+    SteadyBlock,
+    /// Blinking underline.
+    BlinkingUnderline,
+    /// Steady underline.
+    SteadyUnderline,
+    /// Blinking bar.
+    BlinkingBar,
+    /// Steady bar.
+    SteadyBar,
+}
+
+impl CursorStyle {
+    /// Create cursor style from shape and blinking.
+    pub fn new(shape: CursorShape, blinking: bool) -> Self {
+        match (shape, blinking) {
+            (CursorShape::Block, true) => CursorStyle::BlinkingBlock,
+            (CursorShape::Block, false) => CursorStyle::SteadyBlock,
+            (CursorShape::Underline, true) => CursorStyle::BlinkingUnderline,
+            (CursorShape::Underline, false) => CursorStyle::SteadyUnderline,
+            (CursorShape::Bar, true) => CursorStyle::BlinkingBar,
+            (CursorShape::Bar, false) => CursorStyle::SteadyBar,
+        }
+    }
+
+    /// Shape of the cursor.
+    pub fn shape(self) -> CursorShape {
+        match self {
+            CursorStyle::BlinkingBlock | CursorStyle::SteadyBlock => CursorShape::Block,
+            CursorStyle::BlinkingUnderline | CursorStyle::SteadyUnderline => CursorShape::Underline,
+            CursorStyle::BlinkingBar | CursorStyle::SteadyBar => CursorShape::Bar,
+        }
+    }
+
+    /// Whether the cursor blinks.
+    pub fn blinking(self) -> bool {
+        matches!(
+            self,
+            CursorStyle::BlinkingBlock | CursorStyle::BlinkingUnderline | CursorStyle::BlinkingBar
+        )
+    }
+}
+
 /// Mode for clearing line.
 ///
 /// Relative to cursor.
@@ -159,6 +229,15 @@ pub enum ClearMode {
     Saved,
 }
 
+/// Mode for clearing tab stops.
+#[derive(Debug)]
+pub enum TabulationClearMode {
+    /// Clear stop under cursor.
+    Current,
+    /// Clear all stops.
+    All,
+}
+
 /// Terminal character attributes.
 #[derive(Debug, Eq, PartialEq)]
 pub enum Attr {
@@ -244,6 +323,15 @@ pub trait Handler {
     /// Put `count` tabs.
     fn put_tab(&mut self, _count: u16) {}
 
+    /// Move cursor backward `count` tabs.
+    fn move_backward_tabs(&mut self, _count: u16) {}
+
+    /// Set a horizontal tab stop at cursor.
+    fn set_horizontal_tabstop(&mut self) {}
+
+    /// Clear tab stops.
+    fn clear_tabs(&mut self, _mode: TabulationClearMode) {}
+
     /// Backspace `count` characters.
     fn backspace(&mut self) {}
 
@@ -253,6 +341,9 @@ pub trait Handler {
     /// Linefeed.
     fn linefeed(&mut self) {}
 
+    /// Move cursor up one line, scrolling down if it is at the top margin.
+    fn reverse_index(&mut self) {}
+
     /// Scroll up `rows` rows.
     fn scroll_up(&mut self, _rows: usize) {}
 
@@ -271,6 +362,15 @@ pub trait Handler {
     /// to the right of the deleted things is shifted left.
     fn delete_chars(&mut self, _count: usize) {}
 
+    /// Insert `count` blank characters at cursor, shifting the rest of the line right.
+    fn insert_blank(&mut self, _count: usize) {}
+
+    /// Insert `count` blank lines at cursor, shifting lines below down.
+    fn insert_blank_lines(&mut self, _count: usize) {}
+
+    /// Delete `count` lines at cursor, shifting lines below up.
+    fn delete_lines(&mut self, _count: usize) {}
+
     /// Save current cursor position.
     fn save_cursor_position(&mut self) {}
 
@@ -295,8 +395,21 @@ pub trait Handler {
     /// DECSTBM - Set the terminal scrolling region.
     fn set_scrolling_region(&mut self, _top: usize, _bottom: Option<usize>) {}
 
+    /// Set style of the cursor, or reset it to default if `None`.
+    fn set_cursor_style(&mut self, _style: Option<CursorStyle>) {}
+
+    /// Set keypad to application mode (DECKPAM).
+    fn set_keypad_application_mode(&mut self) {}
+
+    /// Set keypad to numeric mode (DECKPNM).
+    fn unset_keypad_application_mode(&mut self) {}
+
     /// Report device status.
     fn device_status(&mut self, _arg: usize) {}
+
+    /// Report device attributes: primary (DA1) for `None`,
+    /// secondary (DA2) for `Some('>')` and tertiary (DA3) for `Some('=')`.
+    fn identify_terminal(&mut self, _intermediate: Option<char>) {}
 }
 
 pub struct Performer<'a, H: Handler> {
@@ -393,6 +506,7 @@ impl<H: Handler> Perform for Performer<'_, H> {
                 .unwrap_or(default)
         };
         match (action, intermediates) {
+            ('@', []) => handler.insert_blank(next_param_or(1) as usize),
             ('A', []) => handler.move_up(next_param_or(1) as usize),
             ('B', []) | ('e', []) => handler.move_down(next_param_or(1) as usize),
             ('C', []) | ('a', []) => handler.move_forward(next_param_or(1) as usize),
@@ -400,6 +514,7 @@ impl<H: Handler> Perform for Performer<'_, H> {
             ('E', []) => handler.move_down_and_cr(next_param_or(1) as usize),
             ('F', []) => handler.move_up_and_cr(next_param_or(1) as usize),
             ('G', []) | ('`', []) => handler.goto_col(next_param_or(1) as usize - 1),
+            ('I', []) => handler.put_tab(next_param_or(1)),
             ('H', []) | ('f', []) => {
                 let y = next_param_or(1) as usize;
                 let x = next_param_or(1) as usize;
@@ -432,11 +547,26 @@ impl<H: Handler> Perform for Performer<'_, H> {
 
                 handler.clear_line(mode);
             }
+            ('L', []) => handler.insert_blank_lines(next_param_or(1) as usize),
+            ('M', []) => handler.delete_lines(next_param_or(1) as usize),
             ('P', []) => handler.delete_chars(next_param_or(1) as usize),
             ('S', []) => handler.scroll_up(next_param_or(1) as usize),
             ('T', []) => handler.scroll_down(next_param_or(1) as usize),
             ('X', []) => handler.erase_chars(next_param_or(1) as usize),
+            ('Z', []) => handler.move_backward_tabs(next_param_or(1)),
             ('d', []) => handler.goto_line(next_param_or(1) as usize - 1),
+            ('g', []) => {
+                let mode = match next_param_or(0) {
+                    0 => TabulationClearMode::Current,
+                    3 => TabulationClearMode::All,
+                    _ => {
+                        unhandled!();
+                        return;
+                    }
+                };
+
+                handler.clear_tabs(mode);
+            }
             ('h', intermediates) => {
                 for param in params_iter.map(|param| param[0]) {
                     match Mode::from_primitive(intermediates.first(), param) {
@@ -463,7 +593,28 @@ impl<H: Handler> Perform for Performer<'_, H> {
                     });
                 }
             }
+            ('c', []) | ('c', [b'>']) | ('c', [b'=']) if next_param_or(0) == 0 => {
+                handler.identify_terminal(intermediates.first().map(|&b| b as char))
+            }
             ('n', []) => handler.device_status(next_param_or(0) as usize),
+            ('q', [b' ']) => {
+                // DECSCUSR (CSI Ps SP q) -- Set Cursor Style.
+                let style = match next_param_or(0) {
+                    0 => None,
+                    1 => Some(CursorStyle::BlinkingBlock),
+                    2 => Some(CursorStyle::SteadyBlock),
+                    3 => Some(CursorStyle::BlinkingUnderline),
+                    4 => Some(CursorStyle::SteadyUnderline),
+                    5 => Some(CursorStyle::BlinkingBar),
+                    6 => Some(CursorStyle::SteadyBar),
+                    _ => {
+                        unhandled!();
+                        return;
+                    }
+                };
+
+                handler.set_cursor_style(style);
+            }
             ('r', []) => {
                 let top = next_param_or(1) as usize;
                 let bottom = params_iter
@@ -491,6 +642,11 @@ impl<H: Handler> Perform for Performer<'_, H> {
         match (byte, intermediates) {
             (b'7', []) => self.handler.save_cursor_position(),
             (b'8', []) => self.handler.restore_cursor_position(),
+            (b'H', []) => self.handler.set_horizontal_tabstop(),
+            (b'M', []) => self.handler.reverse_index(),
+            (b'Z', []) => self.handler.identify_terminal(None),
+            (b'=', []) => self.handler.set_keypad_application_mode(),
+            (b'>', []) => self.handler.unset_keypad_application_mode(),
             _ => unhandled!(),
         }
     }
@@ -0,0 +1,348 @@
+use crate::font::MonoFontSet;
+use alloc::vec::Vec;
+use embedded_graphics::{
+    image::ImageRaw,
+    mono_font::{mapping::GlyphMapping, DecorationDimensions, MonoFont},
+    prelude::Size,
+};
+
+/// Error when parsing a BDF font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BdfError {
+    /// The line with this number (starting from 1) is malformed
+    InvalidLine(usize),
+    /// The font has no `FONTBOUNDINGBOX`
+    MissingBoundingBox,
+    /// The font ends in the middle of a glyph
+    UnexpectedEnd,
+    /// The font has no glyph fitting in a single cell
+    NoGlyphs,
+}
+
+/// A font in Glyph Bitmap Distribution Format (`.bdf`)
+///
+/// Glyphs are stored in sparse tables keyed by `char`, taking the `ENCODING`
+/// of each glyph as its Unicode code point. The cell size is given by
+/// `FONTBOUNDINGBOX`, with the cell width narrowed to the advance of the space
+/// character if the font has one.
+///
+/// Glyphs advancing more than a cell (e.g. CJK in GNU Unifont) are used as the
+/// [wide font](MonoFontSet::wide). Characters without a glyph are drawn with a
+/// fallback glyph, which is U+FFFD or `?` if the font has them, and the first glyph otherwise.
+///
+/// The font is used for all text styles on [`TextOnGraphic`](crate::TextOnGraphic).
+pub struct BdfFont {
+    baseline: u32,
+    narrow: Glyphs,
+    wide: Glyphs,
+}
+
+/// A glyph in the font before it is drawn into a cell
+struct BdfChar<'a> {
+    encoding: Option<char>,
+    advance: Option<u32>,
+    /// Width, height, x offset and y offset of the glyph
+    bbx: (u32, u32, i32, i32),
+    bitmap: Vec<&'a str>,
+}
+
+impl BdfFont {
+    /// Parse a BDF font from `data`
+    pub fn parse(data: &str) -> Result<Self, BdfError> {
+        let mut bounding_box = None;
+        let mut chars = Vec::new();
+        let mut current: Option<BdfChar> = None;
+        let mut in_bitmap = false;
+        for (i, line) in data.lines().enumerate() {
+            let invalid = BdfError::InvalidLine(i + 1);
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or("");
+            if in_bitmap && keyword != "ENDCHAR" {
+                current.as_mut().unwrap().bitmap.push(keyword);
+                continue;
+            }
+            let mut number = || -> Result<i32, BdfError> {
+                words.next().and_then(|w| w.parse().ok()).ok_or(invalid)
+            };
+            match (keyword, current.as_mut()) {
+                ("FONTBOUNDINGBOX", None) => {
+                    bounding_box = Some((number()?, number()?, number()?, number()?));
+                }
+                ("STARTCHAR", None) => {
+                    current = Some(BdfChar {
+                        encoding: None,
+                        advance: None,
+                        bbx: (0, 0, 0, 0),
+                        bitmap: Vec::new(),
+                    });
+                }
+                ("ENCODING", Some(c)) => {
+                    // negative encodings are glyphs without a code point
+                    c.encoding = u32::try_from(number()?).ok().and_then(char::from_u32);
+                }
+                ("DWIDTH", Some(c)) => {
+                    c.advance = Some(u32::try_from(number()?).map_err(|_| invalid)?);
+                }
+                ("BBX", Some(c)) => {
+                    let width = u32::try_from(number()?).map_err(|_| invalid)?;
+                    let height = u32::try_from(number()?).map_err(|_| invalid)?;
+                    c.bbx = (width, height, number()?, number()?);
+                }
+                ("BITMAP", Some(_)) => in_bitmap = true,
+                ("ENDCHAR", Some(_)) => {
+                    in_bitmap = false;
+                    chars.push(current.take().unwrap());
+                }
+                ("STARTCHAR" | "ENDCHAR", _) => return Err(invalid),
+                _ => {}
+            }
+        }
+        if current.is_some() {
+            return Err(BdfError::UnexpectedEnd);
+        }
+        let (width, height, _, y_offset) = bounding_box.ok_or(BdfError::MissingBoundingBox)?;
+        let height = u32::try_from(height).map_err(|_| BdfError::MissingBoundingBox)?;
+        let width = chars
+            .iter()
+            .find(|c| c.encoding == Some(' '))
+            .and_then(|c| c.advance)
+            .or_else(|| u32::try_from(width).ok())
+            .filter(|&width| width > 0 && height > 0)
+            .ok_or(BdfError::MissingBoundingBox)?;
+        let baseline = (height as i32 + y_offset).clamp(0, height as i32) as u32;
+
+        let mut narrow = Glyphs::new(width, height);
+        let mut wide = Glyphs::new(width * 2, height);
+        // the blank glyph makes wide characters without a glyph fall back to the narrow font
+        wide.push();
+        for c in chars.iter() {
+            let encoding = match c.encoding {
+                Some(encoding) => encoding,
+                None => continue,
+            };
+            let glyphs = if c.advance.unwrap_or(c.bbx.0) > width {
+                &mut wide
+            } else {
+                &mut narrow
+            };
+            let index = glyphs.push();
+            glyphs.draw(index, c, baseline);
+            glyphs.chars.push((encoding, index));
+        }
+        if narrow.count() == 0 {
+            return Err(BdfError::NoGlyphs);
+        }
+        narrow.sort();
+        wide.sort();
+        narrow.fallback = narrow
+            .lookup('\u{FFFD}')
+            .or_else(|| narrow.lookup('?'))
+            .unwrap_or(0);
+        Ok(BdfFont {
+            baseline,
+            narrow,
+            wide,
+        })
+    }
+
+    /// Draw characters without a glyph with the glyph of `c`
+    ///
+    /// The fallback glyph is unchanged if the font has no glyph for `c` either.
+    pub fn with_fallback(mut self, c: char) -> Self {
+        if let Some(index) = self.narrow.lookup(c) {
+            self.narrow.fallback = index;
+        }
+        self
+    }
+
+    /// Number of glyphs in the font
+    pub fn glyph_count(&self) -> usize {
+        self.narrow.chars.len() + self.wide.chars.len()
+    }
+}
+
+/// Glyphs of the same size, drawn one below another in a bitmap
+struct Glyphs {
+    bitmap: Vec<u8>,
+    size: Size,
+    /// Characters with their glyph index, sorted by character
+    chars: Vec<(char, usize)>,
+    fallback: usize,
+}
+
+impl Glyphs {
+    fn new(width: u32, height: u32) -> Self {
+        Glyphs {
+            bitmap: Vec::new(),
+            size: Size::new(width, height),
+            chars: Vec::new(),
+            fallback: 0,
+        }
+    }
+
+    /// Number of bytes in a row of pixels
+    fn stride(&self) -> usize {
+        (self.size.width as usize).div_ceil(8)
+    }
+
+    fn count(&self) -> usize {
+        self.bitmap.len() / (self.stride() * self.size.height as usize)
+    }
+
+    /// Add a blank glyph, returning its index
+    fn push(&mut self) -> usize {
+        let index = self.count();
+        let len = self.bitmap.len() + self.stride() * self.size.height as usize;
+        self.bitmap.resize(len, 0);
+        index
+    }
+
+    /// Draw `c` as glyph `index`, clipping pixels out of the cell
+    fn draw(&mut self, index: usize, c: &BdfChar, baseline: u32) {
+        let (width, height, x_offset, y_offset) = c.bbx;
+        let top = baseline as i32 - y_offset - height as i32;
+        for (row, hex) in c.bitmap.iter().take(height as usize).enumerate() {
+            let y = top + row as i32;
+            if y < 0 || y >= self.size.height as i32 {
+                continue;
+            }
+            for col in 0..width as usize {
+                let x = x_offset + col as i32;
+                if x < 0 || x >= self.size.width as i32 {
+                    continue;
+                }
+                let digit = hex
+                    .get(col / 4..col / 4 + 1)
+                    .and_then(|d| u8::from_str_radix(d, 16).ok())
+                    .unwrap_or(0);
+                if digit & (0x8 >> (col % 4)) != 0 {
+                    let row_start =
+                        (index * self.size.height as usize + y as usize) * self.stride();
+                    self.bitmap[row_start + x as usize / 8] |= 0x80 >> (x % 8);
+                }
+            }
+        }
+    }
+
+    /// Sort characters for lookup, keeping the first glyph of characters mapped more than once
+    fn sort(&mut self) {
+        self.chars.sort_by_key(|&(c, _)| c);
+        self.chars.dedup_by_key(|&mut (c, _)| c);
+    }
+
+    /// Index of the glyph for `c`, if there is one
+    fn lookup(&self, c: char) -> Option<usize> {
+        self.chars
+            .binary_search_by_key(&c, |&(c, _)| c)
+            .ok()
+            .map(|i| self.chars[i].1)
+    }
+
+    fn font(&self, baseline: u32) -> MonoFont<'_> {
+        let height = self.size.height;
+        MonoFont {
+            image: ImageRaw::new(&self.bitmap, self.size.width),
+            character_size: self.size,
+            character_spacing: 0,
+            baseline,
+            strikethrough: DecorationDimensions::default_strikethrough(height),
+            underline: DecorationDimensions::new((baseline + 1).min(height - 1), 1),
+            glyph_mapping: self,
+        }
+    }
+}
+
+impl GlyphMapping for Glyphs {
+    fn index(&self, c: char) -> usize {
+        self.lookup(c).unwrap_or(self.fallback)
+    }
+}
+
+impl MonoFontSet for BdfFont {
+    fn regular(&self) -> MonoFont<'_> {
+        self.narrow.font(self.baseline)
+    }
+
+    fn wide(&self) -> Option<MonoFont<'_>> {
+        if self.wide.chars.is_empty() {
+            None
+        } else {
+            Some(self.wide.font(self.baseline))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = "\
+STARTFONT 2.1
+FONT test
+SIZE 4 75 75
+FONTBOUNDINGBOX 8 4 0 -1
+CHARS 2
+STARTCHAR A
+ENCODING 65
+DWIDTH 8 0
+BBX 6 3 1 0
+BITMAP
+30
+48
+FC
+ENDCHAR
+STARTCHAR uni4E2D
+ENCODING 20013
+DWIDTH 16 0
+BBX 16 4 0 -1
+BITMAP
+FFFF
+0180
+0180
+8001
+ENDCHAR
+ENDFONT
+";
+
+    #[test]
+    fn parse_font() {
+        let font = BdfFont::parse(FONT).unwrap();
+        assert_eq!(font.glyph_count(), 2);
+        assert_eq!(font.narrow.bitmap, [0x18, 0x24, 0x7e, 0x00]);
+        assert_eq!(font.narrow.index('A'), 0);
+        assert_eq!(font.narrow.index('x'), 0);
+        let regular = font.regular();
+        assert_eq!(regular.character_size, Size::new(8, 4));
+        assert_eq!(regular.baseline, 3);
+
+        let wide = font.wide().unwrap();
+        assert_eq!(wide.character_size, Size::new(16, 4));
+        assert_eq!(font.wide.index('\u{4e2d}'), 1);
+        // other wide characters get the blank glyph
+        assert_eq!(font.wide.index('\u{4e00}'), 0);
+        assert_eq!(
+            font.wide.bitmap[8..],
+            [0xff, 0xff, 0x01, 0x80, 0x01, 0x80, 0x80, 0x01]
+        );
+    }
+
+    #[test]
+    fn invalid_fonts() {
+        let end = FONT.find("ENDCHAR\nENDFONT").unwrap();
+        assert_eq!(
+            BdfFont::parse(&FONT[..end]).err(),
+            Some(BdfError::UnexpectedEnd)
+        );
+        let font = FONT.replace("FONTBOUNDINGBOX 8 4 0 -1\n", "");
+        assert_eq!(
+            BdfFont::parse(&font).err(),
+            Some(BdfError::MissingBoundingBox)
+        );
+        let font = FONT.replace("BBX 6 3 1 0", "BBX 6 x 1 0");
+        assert_eq!(BdfFont::parse(&font).err(), Some(BdfError::InvalidLine(9)));
+        let font = FONT.replace("ENCODING 65", "STARTCHAR B");
+        assert_eq!(BdfFont::parse(&font).err(), Some(BdfError::InvalidLine(7)));
+        let font = FONT.replace("DWIDTH 8 0", "DWIDTH 16 0");
+        assert_eq!(BdfFont::parse(&font).err(), Some(BdfError::NoGlyphs));
+    }
+}
@@ -0,0 +1,437 @@
+use embedded_graphics::{
+    prelude::{DrawTarget, Point, Size},
+    primitives::Rectangle,
+    Pixel,
+};
+
+/// Weight of a line from the center of the cell to one of its edges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Weight {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+/// Lines of U+2500 to U+257F, as weights of the up, right, down and left arms.
+///
+/// `l` is light, `h` is heavy, `d` is double and `.` is none.
+/// The dashed lines, arcs and diagonals are drawn separately.
+const LINES: [&str; 0x80] = [
+    ".l.l", ".h.h", "l.l.", "h.h.", "", "", "", "", "", "", "", "", // 2500
+    ".ll.", ".hl.", ".lh.", ".hh.", "..ll", "..lh", "..hl", "..hh", // 250C
+    "ll..", "lh..", "hl..", "hh..", "l..l", "l..h", "h..l", "h..h", // 2514
+    "lll.", "lhl.", "hll.", "llh.", "hlh.", "hhl.", "lhh.", "hhh.", // 251C
+    "l.ll", "l.lh", "h.ll", "l.hl", "h.hl", "h.lh", "l.hh", "h.hh", // 2524
+    ".lll", ".llh", ".hll", ".hlh", ".lhl", ".lhh", ".hhl", ".hhh", // 252C
+    "ll.l", "ll.h", "lh.l", "lh.h", "hl.l", "hl.h", "hh.l", "hh.h", // 2534
+    "llll", "lllh", "lhll", "lhlh", "hlll", "llhl", "hlhl", "hllh", // 253C
+    "hhll", "llhh", "lhhl", "hhlh", "lhhh", "hlhh", "hhhl", "hhhh", // 2544
+    "", "", "", "", // 254C
+    ".d.d", "d.d.", ".dl.", ".ld.", ".dd.", "..ld", "..dl", "..dd", // 2550
+    "ld..", "dl..", "dd..", "l..d", "d..l", "d..d", "ldl.", "dld.", // 2558
+    "ddd.", "l.ld", "d.dl", "d.dd", ".dld", ".ldl", ".ddd", "ld.d", // 2560
+    "dl.l", "dd.d", "ldld", "dldl", "dddd", // 2568
+    "", "", "", "", "", "", "", // 256D
+    "...l", "l...", ".l..", "..l.", "...h", "h...", ".h..", "..h.", // 2574
+    ".h.l", "l.h.", ".l.h", "h.l.", // 257C
+];
+
+/// Whether `c` is drawn by [`draw`] rather than the font
+pub fn is_synthesized(c: char) -> bool {
+    matches!(c, '\u{2500}'..='\u{259F}' | '\u{2800}'..='\u{28FF}')
+}
+
+/// Draw box-drawing character, block element or braille pattern `c` filling `cell`
+///
+/// Only the foreground is drawn.
+pub fn draw<D: DrawTarget>(target: &mut D, c: char, cell: Rectangle, color: D::Color) {
+    let mut canvas = Canvas {
+        target,
+        cell,
+        color,
+    };
+    let w = cell.size.width as i32;
+    let h = cell.size.height as i32;
+    let light = ((w + 4) / 10).max(1);
+    let heavy = light * 2 + 1;
+    match c as u32 {
+        // dashed lines
+        0x2504..=0x250B | 0x254C..=0x254F => {
+            let (dashes, offset) = match c as u32 {
+                0x2504..=0x2507 => (3, 0x2504),
+                0x2508..=0x250B => (4, 0x2508),
+                _ => (2, 0x254C),
+            };
+            let kind = c as u32 - offset;
+            let thickness = if kind.is_multiple_of(2) { light } else { heavy };
+            let vertical = kind >= 2;
+            let len = if vertical { h } else { w };
+            for i in 0..dashes {
+                let start = len * i / dashes;
+                let end = len * (i + 1) / dashes;
+                let end = end - ((end - start) / 3).max(1);
+                let (a, b) = center(if vertical { w } else { h }, thickness);
+                if vertical {
+                    canvas.fill(a, start, b, end);
+                } else {
+                    canvas.fill(start, a, end, b);
+                }
+            }
+        }
+        // arcs
+        0x256D..=0x2570 => {
+            let (right, down) = match c {
+                '╭' => (true, true),
+                '╮' => (false, true),
+                '╯' => (false, false),
+                _ => (true, false),
+            };
+            canvas.arc(right, down, light);
+        }
+        // diagonals
+        0x2571..=0x2573 => {
+            if c != '╲' {
+                canvas.diagonal(true, light);
+            }
+            if c != '╱' {
+                canvas.diagonal(false, light);
+            }
+        }
+        0x2500..=0x257F => {
+            let arms = LINES[(c as u32 - 0x2500) as usize];
+            let mut weights = [Weight::None; 4];
+            for (weight, b) in weights.iter_mut().zip(arms.bytes()) {
+                *weight = match b {
+                    b'l' => Weight::Light,
+                    b'h' => Weight::Heavy,
+                    b'd' => Weight::Double,
+                    _ => Weight::None,
+                };
+            }
+            canvas.lines(weights, light, heavy);
+        }
+        // upper half, lower eighths and full block
+        0x2580..=0x2588 => {
+            let eighths = if c == '▀' { 4 } else { c as i32 - 0x2580 };
+            let top = if c == '▀' { 0 } else { h - h * eighths / 8 };
+            let bottom = if c == '▀' { h / 2 } else { h };
+            canvas.fill(0, top, w, bottom);
+        }
+        // left eighths
+        0x2589..=0x258F => {
+            let eighths = 0x2590 - c as i32;
+            canvas.fill(0, 0, w * eighths / 8, h);
+        }
+        0x2590 => canvas.fill(w / 2, 0, w, h),
+        // shades
+        0x2591..=0x2593 => {
+            let shade = c as u32 - 0x2590;
+            canvas.pixels(|x, y| match shade {
+                1 => x % 2 == 0 && y % 2 == 0,
+                2 => (x + y) % 2 == 0,
+                _ => x % 2 == 0 || y % 2 == 0,
+            });
+        }
+        0x2594 => canvas.fill(0, 0, w, h / 8),
+        0x2595 => canvas.fill(w - w / 8, 0, w, h),
+        // quadrants
+        0x2596..=0x259F => {
+            // upper left, upper right, lower left and lower right
+            const QUADRANTS: [u8; 10] = [
+                0b0100, 0b1000, 0b0001, 0b1101, 0b1001, 0b0111, 0b1011, 0b0010, 0b0110, 0b1110,
+            ];
+            let quadrants = QUADRANTS[(c as u32 - 0x2596) as usize];
+            for i in 0..4 {
+                if quadrants & (1 << i) != 0 {
+                    let (x, y) = (i % 2, i / 2);
+                    canvas.fill(w * x / 2, h * y / 2, w * (x + 1) / 2, h * (y + 1) / 2);
+                }
+            }
+        }
+        // braille patterns
+        0x2800..=0x28FF => {
+            // the dots from bit 0 to 7, as (column, row)
+            const DOTS: [(i32, i32); 8] = [
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (1, 0),
+                (1, 1),
+                (1, 2),
+                (0, 3),
+                (1, 3),
+            ];
+            let dots = c as u32 - 0x2800;
+            let size = (w / 4).min(h / 8).max(1);
+            for (i, &(col, row)) in DOTS.iter().enumerate() {
+                if dots & (1 << i) != 0 {
+                    let x = w * (2 * col + 1) / 4 - size / 2;
+                    let y = h * (2 * row + 1) / 8 - size / 2;
+                    canvas.fill(x, y, x + size, y + size);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Start and end of a line of `thickness` centered in `len`
+fn center(len: i32, thickness: i32) -> (i32, i32) {
+    let start = len / 2 - thickness / 2;
+    (start, start + thickness)
+}
+
+/// Cell to draw on, with coordinates relative to its top left
+struct Canvas<'a, D: DrawTarget> {
+    target: &'a mut D,
+    cell: Rectangle,
+    color: D::Color,
+}
+
+impl<D: DrawTarget> Canvas<'_, D> {
+    /// Fill pixels from `(x0, y0)` to `(x1, y1)` exclusive, clipped to the cell
+    fn fill(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        let w = self.cell.size.width as i32;
+        let h = self.cell.size.height as i32;
+        let (x0, x1) = (x0.clamp(0, w), x1.clamp(0, w));
+        let (y0, y1) = (y0.clamp(0, h), y1.clamp(0, h));
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let rect = Rectangle::new(
+            self.cell.top_left + Point::new(x0, y0),
+            Size::new((x1 - x0) as u32, (y1 - y0) as u32),
+        );
+        self.target.fill_solid(&rect, self.color).ok();
+    }
+
+    /// Fill pixels of the cell at which `f` returns `true`
+    fn pixels(&mut self, f: impl Fn(i32, i32) -> bool) {
+        let (top_left, color) = (self.cell.top_left, self.color);
+        let w = self.cell.size.width as i32;
+        let h = self.cell.size.height as i32;
+        let pixels = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .filter(|&(x, y)| f(x, y))
+            .map(|(x, y)| Pixel(top_left + Point::new(x, y), color));
+        self.target.draw_iter(pixels).ok();
+    }
+
+    /// Draw arms from the center with `weights` of up, right, down and left
+    fn lines(&mut self, weights: [Weight; 4], light: i32, heavy: i32) {
+        let w = self.cell.size.width as i32;
+        let h = self.cell.size.height as i32;
+        let [up, right, down, left] = weights;
+        // extent of the arms on an axis, across the axis of length `len`
+        let span = |len: i32, arms: [Weight; 2]| {
+            arms.iter()
+                .filter_map(|&weight| match weight {
+                    Weight::None => None,
+                    Weight::Light => Some(center(len, light)),
+                    Weight::Heavy => Some(center(len, heavy)),
+                    Weight::Double => Some(center(len, light * 3)),
+                })
+                .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+        };
+        let vertical = span(w, [up, down]);
+        let horizontal = span(h, [left, right]);
+
+        // weight, whether vertical, whether from the center to the end of the axis,
+        // arms on the sides of the arm, extent of the arms across
+        let arms = [
+            (up, true, false, [left, right], horizontal),
+            (right, false, true, [up, down], vertical),
+            (down, true, true, [left, right], horizontal),
+            (left, false, false, [up, down], vertical),
+        ];
+        for (weight, is_vertical, forward, sides, across) in arms {
+            let (len, width) = if is_vertical { (h, w) } else { (w, h) };
+            // offsets of the lines from the center, with the arm on the same side
+            let lines: &[(i32, Weight)] = match weight {
+                Weight::None => continue,
+                Weight::Double => &[(-light, sides[0]), (light, sides[1])],
+                _ => &[(0, Weight::None)],
+            };
+            let thickness = if weight == Weight::Heavy {
+                heavy
+            } else {
+                light
+            };
+            for &(offset, side) in lines {
+                let (a, b) = center(width, thickness);
+                let (a, b) = (a + offset, b + offset);
+                let mid = center(len, light);
+                // the part of the line in the center
+                let (start, end) = match across {
+                    // the double line on the same side turns at its inner line
+                    _ if side == Weight::Double => (mid.0 + light, mid.1 - light),
+                    Some(across) => across,
+                    None => mid,
+                };
+                let (s, e) = if forward { (start, len) } else { (0, end) };
+                if is_vertical {
+                    self.fill(a, s, b, e);
+                } else {
+                    self.fill(s, a, e, b);
+                }
+            }
+        }
+    }
+
+    /// Draw a light arc connecting the right or left edge with the bottom or top edge
+    fn arc(&mut self, right: bool, down: bool, light: i32) {
+        let w = self.cell.size.width as i32;
+        let h = self.cell.size.height as i32;
+        let (x0, x1) = center(w, light);
+        let (y0, y1) = center(h, light);
+        let r = (w / 2).min(h / 2).max(light);
+        // the arc is the quarter of a circle touching both lines
+        let cx = if right { x0 + r } else { x1 - 1 - r };
+        let cy = if down { y0 + r } else { y1 - 1 - r };
+        self.pixels(|x, y| {
+            let in_quadrant =
+                (if right { x <= cx } else { x >= cx }) && (if down { y <= cy } else { y >= cy });
+            let d = (x - cx) * (x - cx) + (y - cy) * (y - cy);
+            in_quadrant && d > (r - light) * (r - light) && d <= r * r
+        });
+        if right {
+            self.fill(cx, y0, w, y1);
+        } else {
+            self.fill(0, y0, cx + 1, y1);
+        }
+        if down {
+            self.fill(x0, cy, x1, h);
+        } else {
+            self.fill(x0, 0, x1, cy + 1);
+        }
+    }
+
+    /// Draw a light diagonal from the top right, or from the top left
+    fn diagonal(&mut self, from_right: bool, light: i32) {
+        let w = self.cell.size.width as i32;
+        let h = self.cell.size.height as i32;
+        for y in 0..h {
+            let start = w * y / h;
+            let end = (w * (y + 1) / h).max(start + light);
+            if from_right {
+                self.fill(w - end, y, w - start, y + 1);
+            } else {
+                self.fill(start, y, end, y + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::OriginDimensions;
+
+    /// Pixels of a cell drawn at `(2, 3)`, with whether anything was drawn outside of it
+    struct Pixels {
+        cell: Rectangle,
+        lit: Vec<bool>,
+        outside: bool,
+    }
+
+    impl DrawTarget for Pixels {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(p, color) in pixels {
+                if !self.cell.contains(p) {
+                    self.outside = true;
+                    continue;
+                }
+                let p = p - self.cell.top_left;
+                self.lit[(p.y as u32 * self.cell.size.width + p.x as u32) as usize] = color.is_on();
+            }
+            Ok(())
+        }
+    }
+
+    impl OriginDimensions for Pixels {
+        fn size(&self) -> Size {
+            Size::new(64, 64)
+        }
+    }
+
+    /// Draw `c` on a cell of `width` x `height`
+    fn draw_cell(c: char, width: u32, height: u32) -> Pixels {
+        let cell = Rectangle::new(Point::new(2, 3), Size::new(width, height));
+        let mut pixels = Pixels {
+            cell,
+            lit: vec![false; (width * height) as usize],
+            outside: false,
+        };
+        draw(&mut pixels, c, cell, BinaryColor::On);
+        assert!(!pixels.outside, "{:?} drawn outside of the cell", c);
+        pixels
+    }
+
+    impl Pixels {
+        /// Whether pixel at `(x, y)` is lit
+        fn get(&self, x: u32, y: u32) -> bool {
+            self.lit[(y * self.cell.size.width + x) as usize]
+        }
+
+        /// Whether any pixel is lit in column `x`
+        fn column(&self, x: u32) -> bool {
+            (0..self.cell.size.height).any(|y| self.get(x, y))
+        }
+
+        /// Whether any pixel is lit in row `y`
+        fn row(&self, y: u32) -> bool {
+            (0..self.cell.size.width).any(|x| self.get(x, y))
+        }
+    }
+
+    #[test]
+    fn lines_reach_edges() {
+        for (w, h) in [(6, 10), (9, 18)] {
+            let horizontal = draw_cell('─', w, h);
+            assert!(horizontal.column(0) && horizontal.column(w - 1));
+            assert!(!horizontal.row(0) && !horizontal.row(h - 1));
+            let vertical = draw_cell('│', w, h);
+            assert!(vertical.row(0) && vertical.row(h - 1));
+            assert!(!vertical.column(0) && !vertical.column(w - 1));
+            let cross = draw_cell('┼', w, h);
+            assert!(cross.column(0) && cross.column(w - 1));
+            assert!(cross.row(0) && cross.row(h - 1));
+        }
+    }
+
+    #[test]
+    fn full_block() {
+        let block = draw_cell('█', 6, 10);
+        assert!(block.lit.iter().all(|&lit| lit));
+    }
+
+    #[test]
+    fn quadrant() {
+        let quadrant = draw_cell('▖', 6, 10);
+        for y in 0..10 {
+            for x in 0..6 {
+                assert_eq!(quadrant.get(x, y), x < 3 && y >= 5, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_cells() {
+        for c in ('\u{2500}'..='\u{259F}').chain('\u{2800}'..='\u{28FF}') {
+            assert!(is_synthesized(c));
+            for (w, h) in [(1, 1), (1, 2), (2, 3)] {
+                draw_cell(c, w, h);
+            }
+        }
+    }
+}
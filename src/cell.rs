@@ -16,15 +16,21 @@ bitflags::bitflags! {
         const STRIKEOUT                 = 0b0000_0010_0000_0000;
         const LEADING_WIDE_CHAR_SPACER  = 0b0000_0100_0000_0000;
         const DOUBLE_UNDERLINE          = 0b0000_1000_0000_0000;
+        const BLINK                     = 0b0001_0000_0000_0000;
     }
 }
 
+/// Maximum number of zero-width characters attached to a cell
+const MAX_ZEROWIDTH: usize = 2;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct Cell {
     pub c: char,
     pub fg: Color,
     pub bg: Color,
     pub flags: Flags,
+    /// Zero-width characters drawn on top of `c`, padded with `'\0'`
+    zerowidth: [char; MAX_ZEROWIDTH],
 }
 
 impl Cell {
@@ -34,6 +40,20 @@ impl Cell {
             ..Default::default()
         }
     }
+
+    /// Attach a zero-width character (e.g. a combining mark) to the cell.
+    ///
+    /// It is dropped if the cell is full.
+    pub fn push_zerowidth(&mut self, c: char) {
+        if let Some(slot) = self.zerowidth.iter_mut().find(|slot| **slot == '\0') {
+            *slot = c;
+        }
+    }
+
+    /// Zero-width characters attached to the cell
+    pub fn zerowidth(&self) -> impl Iterator<Item = char> + '_ {
+        self.zerowidth.iter().copied().take_while(|&c| c != '\0')
+    }
 }
 
 impl Default for Cell {
@@ -44,6 +64,7 @@ impl Default for Cell {
             bg: Color::Named(NamedColor::Black),
             fg: Color::Named(NamedColor::BrightWhite),
             flags: Flags::empty(),
+            zerowidth: ['\0'; MAX_ZEROWIDTH],
         }
     }
 }
@@ -1,14 +1,24 @@
-use crate::ansi::{Attr, ClearMode, Handler, LineClearMode, Mode, Performer};
+use crate::ansi::{
+    Attr, ClearMode, CursorShape, CursorStyle, Handler, LineClearMode, Mode, Performer,
+    TabulationClearMode,
+};
 use crate::cell::{Cell, Flags};
 use crate::color::Rgb888;
+use crate::font::{Font9x18, MonoFontSet};
 use crate::graphic::TextOnGraphic;
+use crate::identity::TerminalIdentity;
+use crate::keyboard::{self, Key, Modifiers};
+use crate::mouse::{MouseButton, MouseEncoding, MouseEventKind, MouseMode, MouseProtocol};
 use crate::text_buffer::TextBuffer;
 use crate::text_buffer_cache::TextBufferCache;
 use alloc::collections::VecDeque;
+use alloc::vec::Vec;
 use core::cmp::min;
 use core::fmt;
+use core::ops::Range;
 
-use embedded_graphics::prelude::{DrawTarget, OriginDimensions};
+use embedded_graphics::prelude::{DrawTarget, OriginDimensions, Point};
+use unicode_width::UnicodeWidthChar;
 use vte::Parser;
 
 /// Console
@@ -21,7 +31,7 @@ pub struct Console<T: TextBuffer> {
     inner: ConsoleInner<T>,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 struct Cursor {
     row: usize,
     col: usize,
@@ -32,24 +42,85 @@ struct ConsoleInner<T: TextBuffer> {
     cursor: Cursor,
     /// Saved cursor
     saved_cursor: Cursor,
+    /// cursor style
+    cursor_style: CursorStyle,
+    /// cursor style to reset to
+    default_cursor_style: CursorStyle,
+    /// show cursor (DECTCEM)
+    cursor_visible: bool,
+    /// whether a blinking cursor is in its visible phase
+    blink_on: bool,
+    /// cursor last drawn on the buffer
+    drawn_cursor: (Option<Cursor>, CursorShape),
     /// current attribute template
     temp: Cell,
     /// character buffer
     buf: T,
     /// auto wrap
     auto_wrap: bool,
+    /// insert mode (IRM)
+    insert_mode: bool,
+    /// whether a wide char has been written, so cells must be read to avoid splitting one
+    wide_chars: bool,
+    /// application cursor keys (DECCKM)
+    app_cursor_keys: bool,
+    /// application keypad (DECKPAM)
+    app_keypad: bool,
+    /// mouse events reported and their encoding
+    mouse: MouseProtocol,
+    /// bracketed paste (?2004)
+    bracketed_paste: bool,
+    /// focus reporting (?1004)
+    focus_reporting: bool,
+    /// whether each column has a tab stop
+    tabs: Vec<bool>,
+    /// rows affected by scrolling, set by DECSTBM
+    scroll_region: Range<usize>,
+    /// whether the alternate screen is shown
+    alt_screen: bool,
+    /// identity reported for device attributes
+    identity: TerminalIdentity,
     /// Reported data for CSI Device Status Report
     report: VecDeque<u8>,
 }
 
+/// Default distance between tab stops
+const TAB_SPACES: usize = 8;
+
 /// Console on top of a frame buffer
-pub type ConsoleOnGraphic<D> = Console<TextBufferCache<TextOnGraphic<D>>>;
+pub type ConsoleOnGraphic<D, F = Font9x18> = Console<TextBufferCache<TextOnGraphic<D, F>>>;
 
-impl<D: DrawTarget<Color = Rgb888> + OriginDimensions> Console<TextBufferCache<TextOnGraphic<D>>> {
+impl<D> Console<TextBufferCache<TextOnGraphic<D>>>
+where
+    D: DrawTarget + OriginDimensions,
+    D::Color: From<Rgb888>,
+{
     /// Create a console on top of a frame buffer
     pub fn on_frame_buffer(buffer: D) -> Self {
+        Self::on_frame_buffer_with_font(buffer, Font9x18)
+    }
+}
+
+impl<D, F> Console<TextBufferCache<TextOnGraphic<D, F>>>
+where
+    D: DrawTarget + OriginDimensions,
+    D::Color: From<Rgb888>,
+    F: MonoFontSet,
+{
+    /// Create a console on top of a frame buffer, rendering text with `font`
+    pub fn on_frame_buffer_with_font(buffer: D, font: F) -> Self {
         let size = buffer.size();
-        Self::on_cached_text_buffer(TextOnGraphic::new(buffer, size.width, size.height))
+        Self::on_cached_text_buffer(TextOnGraphic::with_font(
+            buffer,
+            size.width,
+            size.height,
+            font,
+        ))
+    }
+
+    /// Cell at pixel `point` as `(row, col)`, or `None` if it is out of the screen
+    pub fn cell_at(&self, point: Point) -> Option<(usize, usize)> {
+        self.inner.buf.inner().cell_at(point)
     }
 }
 
@@ -58,28 +129,200 @@ impl<T: TextBuffer> Console<TextBufferCache<T>> {
     pub fn on_cached_text_buffer(buffer: T) -> Self {
         Self::on_text_buffer(TextBufferCache::new(buffer))
     }
+
+    /// Set the maximum number of lines kept in scrollback history, or disable it with `0`
+    ///
+    /// The default is [`DEFAULT_HISTORY_SIZE`](crate::DEFAULT_HISTORY_SIZE).
+    pub fn set_history_size(&mut self, lines: usize) {
+        self.inner.buf.set_history_size(lines);
+    }
+
+    /// Number of lines in scrollback history
+    pub fn history_len(&self) -> usize {
+        self.inner.buf.history_len()
+    }
+
+    /// Scroll the view `lines` up into history, or down if `lines` is negative
+    ///
+    /// The view goes back to the bottom on new output.
+    pub fn scroll_view(&mut self, lines: isize) {
+        self.inner.buf.scroll_view(lines);
+    }
+
+    /// Scroll the view back to the bottom
+    pub fn scroll_to_bottom(&mut self) {
+        self.inner.buf.scroll_to_bottom();
+    }
+
+    /// Enable or disable deferred mode
+    ///
+    /// In deferred mode, the screen is only updated on [`flush`](Self::flush).
+    pub fn set_deferred(&mut self, deferred: bool) {
+        self.inner.buf.set_deferred(deferred);
+    }
+
+    /// Update the screen with changes since last flush in deferred mode
+    pub fn flush(&mut self) {
+        self.inner.buf.flush();
+    }
 }
 
 impl<T: TextBuffer> Console<T> {
     /// Create a console on top of a [`TextBuffer`]
+    ///
+    /// Wide chars, combining marks and sequences that move cells around read the buffer back,
+    /// so it must implement [`TextBuffer::read`], or be wrapped in a
+    /// [`TextBufferCache`] with [`Console::on_cached_text_buffer`].
     pub fn on_text_buffer(buffer: T) -> Self {
-        Console {
+        let (width, height) = (buffer.width(), buffer.height());
+        let mut console = Console {
             parser: Parser::new(),
             inner: ConsoleInner {
                 cursor: Cursor::default(),
                 saved_cursor: Cursor::default(),
+                cursor_style: CursorStyle::default(),
+                default_cursor_style: CursorStyle::default(),
+                cursor_visible: true,
+                blink_on: true,
+                drawn_cursor: (None, CursorShape::default()),
                 temp: Cell::default(),
                 buf: buffer,
                 auto_wrap: true,
+                insert_mode: false,
+                wide_chars: false,
+                app_cursor_keys: false,
+                app_keypad: false,
+                mouse: MouseProtocol::default(),
+                bracketed_paste: false,
+                focus_reporting: false,
+                tabs: (0..width).map(|col| col % TAB_SPACES == 0).collect(),
+                scroll_region: 0..height,
+                alt_screen: false,
+                identity: TerminalIdentity::default(),
                 report: VecDeque::new(),
             },
-        }
+        };
+        console.inner.update_cursor();
+        console
+    }
+
+    /// Report `identity` when the program asks for device attributes
+    ///
+    /// The default is a VT220-class terminal with ANSI colors.
+    pub fn with_identity(mut self, identity: TerminalIdentity) -> Self {
+        self.inner.identity = identity;
+        self
     }
 
     /// Write a single `byte` to console
     pub fn write_byte(&mut self, byte: u8) {
         self.parser
             .advance(&mut Performer::new(&mut self.inner), byte);
+        self.inner.update_cursor();
+    }
+
+    /// Write `bytes` to console
+    ///
+    /// The cursor is only redrawn once at the end, so prefer it to [`Console::write_byte`]
+    /// for more than one byte.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let mut performer = Performer::new(&mut self.inner);
+        for &byte in bytes {
+            self.parser.advance(&mut performer, byte);
+        }
+        self.inner.update_cursor();
+    }
+
+    /// Blink the cursor
+    ///
+    /// It should be called periodically by a timer, e.g. every 500ms.
+    pub fn tick(&mut self) {
+        if self.inner.cursor_style.blinking() {
+            self.inner.blink_on = !self.inner.blink_on;
+            self.inner.update_cursor();
+        }
+    }
+
+    /// Set style of the cursor
+    ///
+    /// It is also the style restored when the application resets it.
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        self.inner.default_cursor_style = style;
+        self.inner.set_cursor_style(Some(style));
+        self.inner.update_cursor();
+    }
+
+    /// Current style of the cursor
+    pub fn cursor_style(&self) -> CursorStyle {
+        self.inner.cursor_style
+    }
+
+    /// Send the bytes of a key press to the report queue
+    ///
+    /// Cursor keys and keypad keys are sent as in the mode set by the program.
+    pub fn key_event(&mut self, key: Key, modifiers: Modifiers) {
+        let inner = &mut self.inner;
+        keyboard::encode(
+            key,
+            modifiers,
+            inner.app_cursor_keys,
+            inner.app_keypad,
+            &mut inner.report,
+        );
+    }
+
+    /// Send a mouse event at `(row, col)` to the report queue, if the program asked for it
+    ///
+    /// `button` is `None` for motion without a button held.
+    pub fn mouse_event(
+        &mut self,
+        kind: MouseEventKind,
+        button: Option<MouseButton>,
+        row: usize,
+        col: usize,
+        modifiers: Modifiers,
+    ) {
+        let inner = &mut self.inner;
+        inner
+            .mouse
+            .encode(kind, button, row, col, modifiers, &mut inner.report);
+    }
+
+    /// Whether the program asked for mouse events
+    ///
+    /// Otherwise, the mouse can be used by the console itself, e.g. to scroll history.
+    pub fn mouse_reporting(&self) -> bool {
+        self.inner.mouse.mode != MouseMode::Off
+    }
+
+    /// Send pasted `text` to the report queue
+    ///
+    /// If the program enabled bracketed paste, the text is wrapped in `ESC [ 200 ~` and
+    /// `ESC [ 201 ~`, with ESC removed from it so that it can't end the paste early.
+    /// Otherwise, line breaks are sent as carriage returns like the Enter key.
+    pub fn paste(&mut self, text: &str) {
+        let report = &mut self.inner.report;
+        if self.inner.bracketed_paste {
+            report.extend(b"\x1b[200~");
+            report.extend(text.bytes().filter(|&b| b != 0x1b));
+            report.extend(b"\x1b[201~");
+        } else {
+            let mut bytes = text.bytes().peekable();
+            while let Some(byte) = bytes.next() {
+                if byte == b'\r' && bytes.peek() == Some(&b'\n') {
+                    continue;
+                }
+                report.push_back(if byte == b'\n' { b'\r' } else { byte });
+            }
+        }
+    }
+
+    /// Tell the program that the console gained or lost focus, if it asked for it
+    pub fn focus_changed(&mut self, focused: bool) {
+        if self.inner.focus_reporting {
+            let report: &[u8] = if focused { b"\x1b[I" } else { b"\x1b[O" };
+            self.inner.report.extend(report);
+        }
     }
 
     /// Read result for some commands
@@ -96,39 +339,171 @@ impl<T: TextBuffer> Console<T> {
     pub fn columns(&self) -> usize {
         self.inner.buf.width()
     }
+
+    /// The text buffer written to
+    pub fn buffer(&self) -> &T {
+        &self.inner.buf
+    }
 }
 
 impl<T: TextBuffer> fmt::Write for Console<T> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for byte in s.bytes() {
-            self.write_byte(byte);
-        }
+        self.write_bytes(s.as_bytes());
         Ok(())
     }
 }
 
+impl<T: TextBuffer> ConsoleInner<T> {
+    /// Draw the cursor on the buffer if it has changed
+    fn update_cursor(&mut self) {
+        if self.buf.width() == 0 || self.buf.height() == 0 {
+            // no cell to draw the cursor on
+            return;
+        }
+        let visible = self.cursor_visible && (self.blink_on || !self.cursor_style.blinking());
+        let cursor = Some(Cursor {
+            row: min(self.cursor.row, self.buf.height() - 1),
+            col: min(self.cursor.col, self.buf.width() - 1),
+        })
+        .filter(|_| visible);
+        let shape = self.cursor_style.shape();
+        let drawn = (cursor, shape);
+        if drawn != self.drawn_cursor {
+            self.drawn_cursor = drawn;
+            let pos = cursor.map(|cursor| (cursor.row, cursor.col));
+            self.buf.set_cursor(pos, shape);
+        }
+    }
+
+    /// Scroll the region from `origin` to the bottom margin up by `lines`.
+    fn scroll_up_relative(&mut self, origin: usize, lines: usize) {
+        trace!("Scrolling up relative: origin={}, lines={}", origin, lines);
+        let lines = min(lines, self.scroll_region.end - origin);
+        let bg = self.temp.bg();
+        self.buf
+            .scroll_region(origin, self.scroll_region.end, lines as isize, bg);
+    }
+
+    /// Scroll the region from `origin` to the bottom margin down by `lines`.
+    fn scroll_down_relative(&mut self, origin: usize, lines: usize) {
+        trace!(
+            "Scrolling down relative: origin={}, lines={}",
+            origin,
+            lines
+        );
+        let lines = min(lines, self.scroll_region.end - origin);
+        let bg = self.temp.bg();
+        self.buf
+            .scroll_region(origin, self.scroll_region.end, -(lines as isize), bg);
+    }
+
+    /// Attach a zero-width character to the cell before cursor.
+    fn input_zerowidth(&mut self, c: char) {
+        let row = self.cursor.row;
+        let mut col = min(self.cursor.col, self.buf.width());
+        if col == 0 {
+            return;
+        }
+        col -= 1;
+        if col > 0
+            && self
+                .buf
+                .read(row, col)
+                .flags
+                .contains(Flags::WIDE_CHAR_SPACER)
+        {
+            col -= 1;
+        }
+        let mut cell = self.buf.read(row, col);
+        cell.push_zerowidth(c);
+        self.buf.write(row, col, cell);
+    }
+
+    /// Blank out the remaining halves of wide chars that are partly
+    /// overwritten by writing `width` cells at cursor.
+    fn clear_wide_chars(&mut self, width: usize) {
+        if !self.wide_chars {
+            return;
+        }
+        let row = self.cursor.row;
+        let start = self.cursor.col;
+        let end = start + width;
+        if start > 0
+            && self
+                .buf
+                .read(row, start)
+                .flags
+                .contains(Flags::WIDE_CHAR_SPACER)
+        {
+            let cell = self.buf.read(row, start - 1).bg();
+            self.buf.write(row, start - 1, cell);
+        }
+        if end < self.buf.width() && self.buf.read(row, end - 1).flags.contains(Flags::WIDE_CHAR) {
+            let cell = self.buf.read(row, end).bg();
+            self.buf.write(row, end, cell);
+        }
+    }
+
+    /// Switch between the primary and the alternate screen, filling it with `fill` if given.
+    fn swap_screen(&mut self, fill: Option<Cell>) {
+        trace!("Swapping screen: alternate={}", !self.alt_screen);
+        self.buf.swap_screen(fill);
+        self.alt_screen = !self.alt_screen;
+        self.buf.set_history_enabled(!self.alt_screen);
+    }
+}
+
 impl<T: TextBuffer> Handler for ConsoleInner<T> {
     #[inline]
     fn input(&mut self, c: char) {
         trace!("  [input]: {:?} @ {:?}", c, self.cursor);
-        if self.cursor.col >= self.buf.width() {
+        let width = c.width().unwrap_or(1);
+        if width == 0 {
+            self.input_zerowidth(c);
+            return;
+        }
+        if width > self.buf.width() {
+            debug!("Skipping char wider than the screen: {:?}", c);
+            return;
+        }
+        if self.cursor.col + width > self.buf.width() {
             if !self.auto_wrap {
                 // skip this one
                 return;
             }
-            self.cursor.col = 0;
+            if self.cursor.col < self.buf.width() {
+                // wide char does not fit in this line, leave a spacer before wrapping
+                let mut spacer = self.temp;
+                spacer.flags.insert(Flags::LEADING_WIDE_CHAR_SPACER);
+                self.buf.write(self.cursor.row, self.cursor.col, spacer);
+            }
             self.linefeed();
         }
+        if self.insert_mode {
+            self.insert_blank(width);
+        }
+        self.clear_wide_chars(width);
         let mut temp = self.temp;
         temp.c = c;
-        self.buf.write(self.cursor.row, self.cursor.col, temp);
-        self.cursor.col += 1;
+        if width == 2 {
+            self.wide_chars = true;
+            temp.flags.insert(Flags::WIDE_CHAR);
+            self.buf.write(self.cursor.row, self.cursor.col, temp);
+            // the spacer keeps the character, so that its second half can be redrawn
+            let mut spacer = temp;
+            spacer.flags.remove(Flags::WIDE_CHAR);
+            spacer.flags.insert(Flags::WIDE_CHAR_SPACER);
+            self.buf.write(self.cursor.row, self.cursor.col + 1, spacer);
+        } else {
+            self.buf.write(self.cursor.row, self.cursor.col, temp);
+        }
+        self.cursor.col += width;
     }
 
     #[inline]
     fn goto(&mut self, row: usize, col: usize) {
         trace!("Going to: line={}, col={}", row, col);
-        self.cursor.row = min(row, self.buf.height());
+        self.cursor.row = min(row, self.buf.height().saturating_sub(1));
         self.cursor.col = min(col, self.buf.width());
     }
 
@@ -185,20 +560,62 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
 
     #[inline]
     fn put_tab(&mut self, count: u16) {
-        let mut count = count;
-        let bg = self.temp.bg();
-        while self.cursor.col < self.buf.width() && count > 0 {
-            count -= 1;
+        trace!("Putting tab: {}", count);
+        if self.buf.width() == 0 {
+            return;
+        }
+        let last = self.buf.width() - 1;
+        for _ in 0..count {
+            if self.cursor.col >= last {
+                break;
+            }
             loop {
-                self.buf.write(self.cursor.row, self.cursor.col, bg);
                 self.cursor.col += 1;
-                if self.cursor.col == self.buf.width() || self.cursor.col % 8 == 0 {
+                if self.cursor.col == last || self.tabs[self.cursor.col] {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn move_backward_tabs(&mut self, count: u16) {
+        trace!("Moving backward tabs: {}", count);
+        if self.buf.width() == 0 {
+            return;
+        }
+        self.cursor.col = min(self.cursor.col, self.buf.width() - 1);
+        for _ in 0..count {
+            while self.cursor.col > 0 {
+                self.cursor.col -= 1;
+                if self.tabs[self.cursor.col] {
                     break;
                 }
             }
         }
     }
 
+    #[inline]
+    fn set_horizontal_tabstop(&mut self) {
+        trace!("Setting horizontal tabstop");
+        if let Some(tab) = self.tabs.get_mut(self.cursor.col) {
+            *tab = true;
+        }
+    }
+
+    #[inline]
+    fn clear_tabs(&mut self, mode: TabulationClearMode) {
+        trace!("Clearing tabs: {:?}", mode);
+        match mode {
+            TabulationClearMode::Current => {
+                if let Some(tab) = self.tabs.get_mut(self.cursor.col) {
+                    *tab = false;
+                }
+            }
+            TabulationClearMode::All => self.tabs.fill(false),
+        }
+    }
+
     #[inline]
     fn backspace(&mut self) {
         trace!("Backspace");
@@ -217,21 +634,34 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
     fn linefeed(&mut self) {
         trace!("Linefeed");
         self.cursor.col = 0;
-        if self.cursor.row < self.buf.height() - 1 {
+        let next = self.cursor.row + 1;
+        if next == self.scroll_region.end {
+            self.scroll_up_relative(self.scroll_region.start, 1);
+        } else if next < self.buf.height() {
             self.cursor.row += 1;
-        } else {
-            self.buf.new_line(self.temp);
+        }
+    }
+
+    #[inline]
+    fn reverse_index(&mut self) {
+        trace!("Reverse index");
+        if self.cursor.row == self.scroll_region.start {
+            self.scroll_down_relative(self.scroll_region.start, 1);
+        } else if self.cursor.row > 0 {
+            self.cursor.row -= 1;
         }
     }
 
     #[inline]
     fn scroll_up(&mut self, rows: usize) {
-        debug!("[Unhandled CSI] scroll_up {:?}", rows);
+        trace!("Scrolling up: {}", rows);
+        self.scroll_up_relative(self.scroll_region.start, rows);
     }
 
     #[inline]
     fn scroll_down(&mut self, rows: usize) {
-        debug!("[Unhandled CSI] scroll_down {:?}", rows);
+        trace!("Scrolling down: {}", rows);
+        self.scroll_down_relative(self.scroll_region.start, rows);
     }
 
     #[inline]
@@ -263,6 +693,66 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
         }
     }
 
+    #[inline]
+    fn insert_blank(&mut self, count: usize) {
+        trace!("Inserting blank: count={}, col={}", count, self.cursor.col);
+        let columns = self.buf.width();
+        let start = min(self.cursor.col, columns);
+        let count = min(count, columns - start);
+        let row = self.cursor.row;
+        if count == 0 {
+            return;
+        }
+
+        // Don't split a wide char at cursor.
+        if start > 0
+            && self
+                .buf
+                .read(row, start)
+                .flags
+                .contains(Flags::WIDE_CHAR_SPACER)
+        {
+            let cell = self.buf.read(row, start - 1).bg();
+            self.buf.write(row, start - 1, cell);
+            let cell = self.buf.read(row, start).bg();
+            self.buf.write(row, start, cell);
+        }
+        for i in (start + count..columns).rev() {
+            self.buf.write(row, i, self.buf.read(row, i - count));
+        }
+        // Inserted cells have current background color set.
+        let bg = self.temp.bg();
+        for i in start..start + count {
+            self.buf.write(row, i, bg);
+        }
+        // A wide char whose spacer was pushed past the margin is blanked.
+        let last = self.buf.read(row, columns - 1);
+        if last.flags.contains(Flags::WIDE_CHAR) {
+            self.buf.write(row, columns - 1, last.bg());
+        }
+    }
+
+    #[inline]
+    fn insert_blank_lines(&mut self, count: usize) {
+        trace!("Inserting blank lines: {}", count);
+        let origin = self.cursor.row;
+        if self.scroll_region.contains(&origin) {
+            self.scroll_down_relative(origin, count);
+        }
+    }
+
+    #[inline]
+    fn delete_lines(&mut self, count: usize) {
+        trace!("Deleting lines: {}", count);
+        let origin = self.cursor.row;
+        if self.scroll_region.contains(&origin) {
+            // deleted lines are not scrolled off the screen, keep them out of history
+            self.buf.set_history_enabled(false);
+            self.scroll_up_relative(origin, count);
+            self.buf.set_history_enabled(!self.alt_screen);
+        }
+    }
+
     /// Save current cursor position.
     fn save_cursor_position(&mut self) {
         trace!("Saving cursor position");
@@ -329,7 +819,7 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
                 self.buf.clear(bg);
                 self.cursor = Cursor::default();
             }
-            _ => {}
+            ClearMode::Saved => self.buf.clear_history(),
         }
     }
 
@@ -354,6 +844,8 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
             Attr::CancelHidden => self.temp.flags.remove(Flags::HIDDEN),
             Attr::Strike => self.temp.flags.insert(Flags::STRIKEOUT),
             Attr::CancelStrike => self.temp.flags.remove(Flags::STRIKEOUT),
+            Attr::BlinkSlow | Attr::BlinkFast => self.temp.flags.insert(Flags::BLINK),
+            Attr::CancelBlink => self.temp.flags.remove(Flags::BLINK),
             _ => {
                 debug!("Term got unhandled attr: {:?}", attr);
             }
@@ -362,29 +854,114 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
 
     #[inline]
     fn set_mode(&mut self, mode: Mode) {
-        if mode == Mode::LineWrap {
-            self.auto_wrap = true;
-        } else {
-            debug!("[Unhandled CSI] Setting mode: {:?}", mode);
+        trace!("Setting mode: {:?}", mode);
+        match mode {
+            Mode::LineWrap => self.auto_wrap = true,
+            Mode::Insert => self.insert_mode = true,
+            Mode::CursorKeys => self.app_cursor_keys = true,
+            Mode::ReportMouseX10 => self.mouse.mode = MouseMode::X10,
+            Mode::ReportMouseClicks => self.mouse.mode = MouseMode::Normal,
+            Mode::ReportCellMouseMotion => self.mouse.mode = MouseMode::ButtonEvent,
+            Mode::ReportAllMouseMotion => self.mouse.mode = MouseMode::AnyEvent,
+            Mode::Utf8Mouse => self.mouse.encoding = MouseEncoding::Utf8,
+            Mode::SgrMouse => self.mouse.encoding = MouseEncoding::Sgr,
+            Mode::BracketedPaste => self.bracketed_paste = true,
+            Mode::ReportFocusInOut => self.focus_reporting = true,
+            Mode::ShowCursor => self.cursor_visible = true,
+            Mode::BlinkingCursor => {
+                self.cursor_style = CursorStyle::new(self.cursor_style.shape(), true);
+                self.blink_on = true;
+            }
+            Mode::SwapScreenAndSetRestoreCursor => {
+                if self.alt_screen {
+                    self.buf.clear(self.temp.bg());
+                } else {
+                    self.save_cursor_position();
+                    self.swap_screen(Some(self.temp.bg()));
+                }
+            }
+            Mode::SwapScreen | Mode::SwapScreenAndClear => {
+                if !self.alt_screen {
+                    self.swap_screen(None);
+                }
+            }
+            _ => debug!("[Unhandled CSI] Setting mode: {:?}", mode),
         }
     }
 
     #[inline]
     fn unset_mode(&mut self, mode: Mode) {
-        if mode == Mode::LineWrap {
-            self.auto_wrap = false;
-        } else {
-            debug!("[Unhandled CSI] Setting mode: {:?}", mode);
+        trace!("Unsetting mode: {:?}", mode);
+        match mode {
+            Mode::LineWrap => self.auto_wrap = false,
+            Mode::Insert => self.insert_mode = false,
+            Mode::CursorKeys => self.app_cursor_keys = false,
+            Mode::ReportMouseX10
+            | Mode::ReportMouseClicks
+            | Mode::ReportCellMouseMotion
+            | Mode::ReportAllMouseMotion => self.mouse.mode = MouseMode::Off,
+            Mode::Utf8Mouse | Mode::SgrMouse => self.mouse.encoding = MouseEncoding::Default,
+            Mode::BracketedPaste => self.bracketed_paste = false,
+            Mode::ReportFocusInOut => self.focus_reporting = false,
+            Mode::ShowCursor => self.cursor_visible = false,
+            Mode::BlinkingCursor => {
+                self.cursor_style = CursorStyle::new(self.cursor_style.shape(), false);
+            }
+            Mode::SwapScreenAndSetRestoreCursor => {
+                if self.alt_screen {
+                    self.swap_screen(None);
+                    self.restore_cursor_position();
+                }
+            }
+            Mode::SwapScreen => {
+                if self.alt_screen {
+                    self.swap_screen(None);
+                }
+            }
+            Mode::SwapScreenAndClear => {
+                if self.alt_screen {
+                    self.buf.clear(self.temp.bg());
+                    self.swap_screen(None);
+                }
+            }
+            _ => debug!("[Unhandled CSI] Unsetting mode: {:?}", mode),
         }
     }
 
     #[inline]
     fn set_scrolling_region(&mut self, top: usize, bottom: Option<usize>) {
-        let bottom = bottom.unwrap_or_else(|| self.buf.height());
-        debug!(
-            "[Unhandled CSI] Setting scrolling region: ({};{})",
-            top, bottom
-        );
+        // Fallback to the last line as default.
+        let height = self.buf.height();
+        let bottom = bottom.unwrap_or(height);
+        // `top` and `bottom` are 1-based and inclusive.
+        let region = min(top.saturating_sub(1), height)..min(bottom, height);
+        if top == 0 || region.start >= region.end {
+            debug!("Invalid scrolling region: ({};{})", top, bottom);
+            return;
+        }
+        trace!("Setting scrolling region: ({};{})", top, bottom);
+
+        self.scroll_region = region;
+        self.goto(0, 0);
+    }
+
+    #[inline]
+    fn set_cursor_style(&mut self, style: Option<CursorStyle>) {
+        trace!("Setting cursor style: {:?}", style);
+        self.cursor_style = style.unwrap_or(self.default_cursor_style);
+        self.blink_on = true;
+    }
+
+    #[inline]
+    fn set_keypad_application_mode(&mut self) {
+        trace!("Setting keypad application mode");
+        self.app_keypad = true;
+    }
+
+    #[inline]
+    fn unset_keypad_application_mode(&mut self) {
+        trace!("Unsetting keypad application mode");
+        self.app_keypad = false;
     }
 
     #[inline]
@@ -405,4 +982,266 @@ impl<T: TextBuffer> Handler for ConsoleInner<T> {
             _ => debug!("unknown device status query: {}", arg),
         }
     }
+
+    #[inline]
+    fn identify_terminal(&mut self, intermediate: Option<char>) {
+        trace!("Reporting device attributes: {:?}", intermediate);
+        self.identity.encode(intermediate, &mut self.report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GridBuffer, DEFAULT_HISTORY_SIZE};
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    type TestConsole = Console<TextBufferCache<GridBuffer>>;
+
+    /// Console of `width` columns and `height` rows, with `input` written to it
+    fn console(width: usize, height: usize, input: &str) -> TestConsole {
+        let mut console = Console::on_cached_text_buffer(GridBuffer::new(width, height));
+        console.write_str(input).unwrap();
+        console
+    }
+
+    /// Characters on the screen, one line per row
+    fn text(console: &TestConsole) -> String {
+        console.buffer().inner().text()
+    }
+
+    #[test]
+    fn delete_lines_skip_history() {
+        let mut console = console(4, 3, "a\r\nb\r\nc\x1b[H\x1b[M");
+        assert_eq!(text(&console), "b\nc\n\n");
+        assert_eq!(console.history_len(), 0);
+        console.write_str("\x1b[3H\n").unwrap();
+        assert_eq!(console.history_len(), 1);
+    }
+
+    /// Bytes in the report queue of `console`
+    fn reports(console: &mut TestConsole) -> Vec<u8> {
+        core::iter::from_fn(|| console.pop_report()).collect()
+    }
+
+    #[test]
+    fn application_keys() {
+        let mut console = console(4, 1, "\x1b[?1h\x1b=");
+        console.key_event(Key::Up, Modifiers::empty());
+        console.key_event(Key::Keypad('5'), Modifiers::empty());
+        assert_eq!(reports(&mut console), b"\x1bOA\x1bOu");
+        console.write_str("\x1b[?1l\x1b>").unwrap();
+        console.key_event(Key::Up, Modifiers::empty());
+        console.key_event(Key::Keypad('5'), Modifiers::empty());
+        assert_eq!(reports(&mut console), b"\x1b[A5");
+    }
+
+    #[test]
+    fn mouse_reporting() {
+        let mut console = console(4, 1, "");
+        let none = Modifiers::empty();
+        assert!(!console.mouse_reporting());
+        console.mouse_event(MouseEventKind::Press, Some(MouseButton::Left), 0, 1, none);
+        assert_eq!(reports(&mut console), b"");
+        console.write_str("\x1b[?9h").unwrap();
+        console.mouse_event(MouseEventKind::Press, Some(MouseButton::Left), 0, 1, none);
+        assert_eq!(reports(&mut console), b"\x1b[M \"!");
+        console.write_str("\x1b[?1000h\x1b[?1006h").unwrap();
+        assert!(console.mouse_reporting());
+        console.mouse_event(MouseEventKind::Release, Some(MouseButton::Left), 0, 1, none);
+        assert_eq!(reports(&mut console), b"\x1b[<0;2;1m");
+        console.write_str("\x1b[?1000l").unwrap();
+        assert!(!console.mouse_reporting());
+    }
+
+    #[test]
+    fn device_attributes() {
+        let mut console = console(4, 1, "\x1b[c\x1b[0c");
+        assert_eq!(reports(&mut console), b"\x1b[?62;22c\x1b[?62;22c");
+        console.write_str("\x1b[>c\x1b[=c").unwrap();
+        assert_eq!(reports(&mut console), b"\x1b[>1;0;0c\x1bP!|00000000\x1b\\");
+        let identity = TerminalIdentity {
+            conformance_level: 64,
+            ..TerminalIdentity::default()
+        };
+        let mut console = console.with_identity(identity);
+        console.write_str("\x1b[c").unwrap();
+        assert_eq!(reports(&mut console), b"\x1b[?64;22c");
+    }
+
+    #[test]
+    fn history_size() {
+        let mut console = console(4, 1, "");
+        for _ in 0..DEFAULT_HISTORY_SIZE + 10 {
+            console.write_str("x\n").unwrap();
+        }
+        assert_eq!(console.history_len(), DEFAULT_HISTORY_SIZE);
+        console.set_history_size(0);
+        assert_eq!(console.history_len(), 0);
+        console.write_str("x\n").unwrap();
+        assert_eq!(console.history_len(), 0);
+    }
+
+    #[test]
+    fn cursor_below_screen() {
+        let written = console(4, 3, "\x1b[99;1Hx");
+        assert_eq!(text(&written), "\n\nx\n");
+        let cleared = console(4, 3, "ab\x1b[9;1H\x1b[J");
+        assert_eq!(text(&cleared), "ab\n\n\n");
+    }
+
+    #[test]
+    fn scrolling_region() {
+        let mut console = console(4, 4, "1\r\n2\r\n3\r\n4\x1b[2;3r\x1b[3H\nX");
+        assert_eq!(text(&console), "1\n3\nX\n4\n");
+        console.write_str("\x1b[2H\x1bM").unwrap();
+        assert_eq!(text(&console), "1\n\n3\n4\n");
+        assert_eq!(console.history_len(), 0);
+    }
+
+    #[test]
+    fn scrolling_region_out_of_screen() {
+        let ignored = console(4, 3, "a\r\nb\r\nc\x1b[5;9r\x1b[3H\nd");
+        assert_eq!(text(&ignored), "b\nc\nd\n");
+        let clamped = console(4, 3, "a\r\nb\r\nc\x1b[2;9r\x1b[3H\nd");
+        assert_eq!(text(&clamped), "a\nc\nd\n");
+    }
+
+    #[test]
+    fn scroll_up_and_down() {
+        let mut console = console(4, 3, "a\r\nb\r\nc\x1b[S");
+        assert_eq!(text(&console), "b\nc\n\n");
+        console.write_str("\x1b[2T").unwrap();
+        assert_eq!(text(&console), "\n\nb\n");
+    }
+
+    #[test]
+    fn alternate_screen() {
+        let mut console = console(8, 2, "main\x1b[?1049h");
+        assert_eq!(text(&console), "\n\n");
+        console.write_str("\x1b[Halt\r\n\n\n").unwrap();
+        assert_eq!(console.history_len(), 0);
+        console.write_str("\x1b[?1049l!").unwrap();
+        assert_eq!(text(&console), "main!\n\n");
+    }
+
+    #[test]
+    fn history_view() {
+        let mut console = console(4, 2, "1\r\n2\r\n3\r\n4");
+        assert_eq!(console.history_len(), 2);
+        assert_eq!(text(&console), "3\n4\n");
+        console.scroll_view(1);
+        assert_eq!(text(&console), "2\n3\n");
+        console.scroll_view(5);
+        assert_eq!(text(&console), "1\n2\n");
+        console.write_str("5").unwrap();
+        assert_eq!(text(&console), "3\n45\n");
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let mut console = console(4, 3, "a\r\nb\r\nc\x1b[2H\x1b[L");
+        assert_eq!(text(&console), "a\n\nb\n");
+        console.write_str("\x1b[M").unwrap();
+        assert_eq!(text(&console), "a\nb\n\n");
+        console.write_str("\x1b[Hxyz\x1b[1;2H\x1b[2@").unwrap();
+        assert_eq!(text(&console), "x  y\nb\n\n");
+    }
+
+    #[test]
+    fn insert_mode() {
+        let mut console = console(5, 1, "abc\x1b[H\x1b[4hX");
+        assert_eq!(text(&console), "Xabc\n");
+        console.write_str("\x1b[4lY").unwrap();
+        assert_eq!(text(&console), "XYbc\n");
+    }
+
+    #[test]
+    fn insert_blank_wide_chars() {
+        let pushed = console(4, 1, "a\u{4e2d}\x1b[H\x1b[4hXY");
+        assert_eq!(text(&pushed), "XYa\n");
+        let orphaned = console(4, 1, "\u{4e2d}b\x1b[1;2H\x1b[@");
+        assert_eq!(text(&orphaned), "   b\n");
+    }
+
+    #[test]
+    fn tabs_without_columns() {
+        let empty = console(0, 1, "\t\x1b[Z");
+        assert_eq!(text(&empty), "\n");
+    }
+
+    #[test]
+    fn tab_stops() {
+        let default = console(20, 1, "\tA\tB");
+        assert_eq!(text(&default), "        A       B\n");
+        let set = console(20, 1, "\x1b[3g\x1b[4G\x1bH\x1b[12G\x1bH\r\x1b[2IA\x1b[2ZB");
+        assert_eq!(text(&set), "   B       A\n");
+        let cleared = console(20, 1, "\x1b[9G\x1b[g\r\tA");
+        assert_eq!(text(&cleared), "                A\n");
+    }
+
+    #[test]
+    fn wide_char_on_one_column() {
+        let narrow = console(1, 2, "\u{4e2d}a");
+        assert_eq!(text(&narrow), "a\n\n");
+    }
+
+    #[test]
+    fn wide_char_wraps() {
+        let console = console(5, 2, "abcd\u{4e2d}x");
+        assert_eq!(text(&console), "abcd\n\u{4e2d}x\n");
+        assert_eq!(console.buffer().inner().cursor(), Some((1, 3)));
+    }
+
+    #[test]
+    fn combining_marks() {
+        let console = console(5, 1, "e\u{301}\u{4e2d}\u{300}x");
+        assert_eq!(text(&console), "e\u{301}\u{4e2d}\u{300}x\n");
+    }
+
+    #[test]
+    fn deferred_flush() {
+        let input = "\x1b[31mred\x1b[0m\r\n\u{4e2d}\x1b[1mbold\r\nthird\x1b[1;2r\x1b[2H\nx\x1b[r\x1b[4h\x1b[Hz";
+        let immediate = console(6, 3, input);
+        let mut deferred = console(6, 3, "");
+        deferred.set_deferred(true);
+        deferred.write_str(input).unwrap();
+        assert_eq!(text(&deferred), "\n\n\n");
+        deferred.flush();
+        let (immediate, deferred) = (immediate.buffer().inner(), deferred.buffer().inner());
+        assert_eq!(
+            deferred.text_with_attributes(),
+            immediate.text_with_attributes()
+        );
+        assert_eq!(deferred.cursor(), immediate.cursor());
+    }
+
+    #[test]
+    fn paste() {
+        let mut plain = console(4, 1, "");
+        plain.paste("a\nb\r\nc\rd");
+        assert_eq!(reports(&mut plain), b"a\rb\rc\rd");
+
+        let mut bracketed = console(4, 1, "\x1b[?2004h");
+        bracketed.paste("a\x1b[201~b\n");
+        assert_eq!(reports(&mut bracketed), b"\x1b[200~a[201~b\n\x1b[201~");
+        bracketed.write_str("\x1b[?2004l").unwrap();
+        bracketed.paste("a\n");
+        assert_eq!(reports(&mut bracketed), b"a\r");
+    }
+
+    #[test]
+    fn focus_reporting() {
+        let mut focus = console(4, 1, "");
+        focus.focus_changed(true);
+        assert_eq!(reports(&mut focus), b"");
+        focus.write_str("\x1b[?1004h").unwrap();
+        focus.focus_changed(false);
+        focus.focus_changed(true);
+        assert_eq!(reports(&mut focus), b"\x1b[O\x1b[I");
+        focus.write_str("\x1b[?1004l").unwrap();
+        focus.focus_changed(false);
+        assert_eq!(reports(&mut focus), b"");
+    }
 }
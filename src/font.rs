@@ -0,0 +1,164 @@
+use embedded_graphics::{
+    mono_font::{
+        iso_8859_1::{FONT_9X18, FONT_9X18_BOLD},
+        MonoFont,
+    },
+    prelude::Size,
+};
+
+/// Monospaced fonts to render each text style on [`TextOnGraphic`](crate::TextOnGraphic)
+///
+/// All fonts should have the same character size,
+/// except the wide font which should be twice as wide.
+///
+/// [`MonoFont`] can't be shared between threads. To keep the console `Send`,
+/// implement this trait on a unit struct returning constant fonts, like [`Font9x18`] does.
+pub trait MonoFontSet {
+    /// Font for regular text
+    fn regular(&self) -> MonoFont<'_>;
+
+    /// Font for bold text
+    fn bold(&self) -> MonoFont<'_> {
+        self.regular()
+    }
+
+    /// Font for italic text
+    fn italic(&self) -> MonoFont<'_> {
+        self.regular()
+    }
+
+    /// Font for bold and italic text
+    fn bold_italic(&self) -> MonoFont<'_> {
+        self.bold()
+    }
+
+    /// Font for wide characters (e.g. CJK)
+    ///
+    /// Wide characters without a glyph in it are drawn with the regular fonts
+    /// in the first of their two cells.
+    fn wide(&self) -> Option<MonoFont<'_>> {
+        None
+    }
+
+    /// Size of a character cell in pixels
+    fn char_size(&self) -> Size {
+        let font = self.regular();
+        Size::new(
+            font.character_size.width + font.character_spacing,
+            font.character_size.height,
+        )
+    }
+}
+
+impl<F: MonoFontSet + ?Sized> MonoFontSet for &F {
+    fn regular(&self) -> MonoFont<'_> {
+        (**self).regular()
+    }
+
+    fn bold(&self) -> MonoFont<'_> {
+        (**self).bold()
+    }
+
+    fn italic(&self) -> MonoFont<'_> {
+        (**self).italic()
+    }
+
+    fn bold_italic(&self) -> MonoFont<'_> {
+        (**self).bold_italic()
+    }
+
+    fn wide(&self) -> Option<MonoFont<'_>> {
+        (**self).wide()
+    }
+
+    fn char_size(&self) -> Size {
+        (**self).char_size()
+    }
+}
+
+/// The 9x18 ISO-8859-1 font from [`embedded_graphics`] with its bold variant
+///
+/// This is the default font of [`TextOnGraphic`](crate::TextOnGraphic).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Font9x18;
+
+impl MonoFontSet for Font9x18 {
+    fn regular(&self) -> MonoFont<'_> {
+        FONT_9X18
+    }
+
+    fn bold(&self) -> MonoFont<'_> {
+        FONT_9X18_BOLD
+    }
+}
+
+/// A set of [`MonoFont`]s chosen at runtime
+///
+/// Styles without a font fall back to the regular font.
+#[derive(Clone, Copy)]
+pub struct MonoFonts<'a> {
+    regular: MonoFont<'a>,
+    bold: Option<MonoFont<'a>>,
+    italic: Option<MonoFont<'a>>,
+    bold_italic: Option<MonoFont<'a>>,
+    wide: Option<MonoFont<'a>>,
+}
+
+impl<'a> MonoFonts<'a> {
+    /// Create a font set with only a regular font
+    pub fn new(regular: MonoFont<'a>) -> Self {
+        MonoFonts {
+            regular,
+            bold: None,
+            italic: None,
+            bold_italic: None,
+            wide: None,
+        }
+    }
+
+    /// Set font for bold text
+    pub fn with_bold(mut self, font: MonoFont<'a>) -> Self {
+        self.bold = Some(font);
+        self
+    }
+
+    /// Set font for italic text
+    pub fn with_italic(mut self, font: MonoFont<'a>) -> Self {
+        self.italic = Some(font);
+        self
+    }
+
+    /// Set font for bold and italic text
+    pub fn with_bold_italic(mut self, font: MonoFont<'a>) -> Self {
+        self.bold_italic = Some(font);
+        self
+    }
+
+    /// Set font for wide characters
+    pub fn with_wide(mut self, font: MonoFont<'a>) -> Self {
+        self.wide = Some(font);
+        self
+    }
+}
+
+impl MonoFontSet for MonoFonts<'_> {
+    fn regular(&self) -> MonoFont<'_> {
+        self.regular
+    }
+
+    fn bold(&self) -> MonoFont<'_> {
+        self.bold.unwrap_or(self.regular)
+    }
+
+    fn italic(&self) -> MonoFont<'_> {
+        self.italic.unwrap_or(self.regular)
+    }
+
+    fn bold_italic(&self) -> MonoFont<'_> {
+        self.bold_italic.unwrap_or_else(|| self.bold())
+    }
+
+    fn wide(&self) -> Option<MonoFont<'_>> {
+        self.wide
+    }
+}
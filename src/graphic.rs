@@ -1,30 +1,183 @@
+use crate::ansi::CursorShape;
+use crate::box_drawing;
 use crate::cell::{Cell, Flags};
+use crate::font::{Font9x18, MonoFontSet};
 use crate::text_buffer::TextBuffer;
+use alloc::string::String;
+use core::cmp::min;
 use embedded_graphics::{
-    mono_font::{
-        iso_8859_1::{FONT_9X18 as FONT, FONT_9X18_BOLD as FONT_BOLD},
-        MonoTextStyleBuilder,
-    },
-    pixelcolor::Rgb888,
-    prelude::{DrawTarget, Drawable, Point, Size},
+    mono_font::{MonoFont, MonoTextStyleBuilder},
+    pixelcolor::{Gray8, GrayColor, PixelColor, Rgb888, RgbColor},
+    prelude::{DrawTarget, DrawTargetExt, Drawable, Point, Size},
+    primitives::Rectangle,
     text::{Baseline, Text, TextStyle},
 };
 
-const CHAR_SIZE: Size = FONT.character_size;
+/// Thickness of underline and bar cursors in pixels
+const CURSOR_THICKNESS: u32 = 2;
+
+/// Top left point of the cell at `(row, col)`
+fn cell_point(char_size: Size, row: usize, col: usize) -> Point {
+    Point::new(
+        col as i32 * char_size.width as i32,
+        row as i32 * char_size.height as i32,
+    )
+}
+
+/// A character which no font should have a glyph for
+const UNMAPPED_CHAR: char = '\u{10FFFF}';
+
+/// Whether `font` has a glyph for `c`, rather than a replacement glyph
+fn has_glyph(font: &MonoFont, c: char) -> bool {
+    font.glyph_mapping.index(c) != font.glyph_mapping.index(UNMAPPED_CHAR)
+}
+
+/// Foreground and background colors of `cell` on the frame buffer
+///
+/// If the frame buffer can't tell them apart, e.g. on a monochrome display,
+/// the brighter one is drawn in white and the other in black.
+fn cell_colors<C: PixelColor + From<Rgb888>>(cell: &Cell) -> (C, C) {
+    let (fg, bg) = if cell.flags.contains(Flags::INVERSE) {
+        (cell.bg.to_rgb(), cell.fg.to_rgb())
+    } else {
+        (cell.fg.to_rgb(), cell.bg.to_rgb())
+    };
+    let (fg_color, bg_color) = (C::from(fg), C::from(bg));
+    if fg_color != bg_color || fg == bg {
+        (fg_color, bg_color)
+    } else if Gray8::from(fg).luma() >= Gray8::from(bg).luma() {
+        (Rgb888::WHITE.into(), Rgb888::BLACK.into())
+    } else {
+        (Rgb888::BLACK.into(), Rgb888::WHITE.into())
+    }
+}
+
+/// Font in `fonts` for text with `flags`
+fn style_font<F: MonoFontSet>(fonts: &F, flags: Flags) -> MonoFont<'_> {
+    if flags.contains(Flags::BOLD_ITALIC) {
+        fonts.bold_italic()
+    } else if flags.contains(Flags::BOLD) {
+        fonts.bold()
+    } else if flags.contains(Flags::ITALIC) {
+        fonts.italic()
+    } else {
+        fonts.regular()
+    }
+}
+
+/// Draw `s` on `target` starting at `point`, with `font` and the colors and styles of `cell`
+fn draw_text<D>(target: &mut D, font: &MonoFont, point: Point, s: &str, cell: &Cell)
+where
+    D: DrawTarget,
+    D::Color: From<Rgb888>,
+{
+    let (fg, bg) = cell_colors::<D::Color>(cell);
+    let mut style = MonoTextStyleBuilder::new()
+        .font(font)
+        .text_color(fg)
+        .background_color(bg);
+    if cell.flags.contains(Flags::STRIKEOUT) {
+        style = style.strikethrough();
+    }
+    if cell.flags.contains(Flags::UNDERLINE) {
+        style = style.underline();
+    }
+    let text = Text::with_text_style(
+        s,
+        point,
+        style.build(),
+        TextStyle::with_baseline(Baseline::Top),
+    );
+    text.draw(target).ok();
+}
+
+/// Whether `cell` can be drawn in a run with others, i.e. it is a single character in one cell
+fn is_plain(cell: &Cell) -> bool {
+    !cell
+        .flags
+        .intersects(Flags::WIDE_CHAR | Flags::WIDE_CHAR_SPACER)
+        && !box_drawing::is_synthesized(cell.c)
+        && cell.zerowidth().next().is_none()
+}
+
+/// Draw `cell` on `target` with its top left corner at `point`
+fn draw_cell<D, F>(target: &mut D, fonts: &F, point: Point, cell: Cell)
+where
+    D: DrawTarget,
+    D::Color: From<Rgb888>,
+    F: MonoFontSet,
+{
+    // box-drawing characters are drawn over a blank to fill the whole cell
+    let synthesized = box_drawing::is_synthesized(cell.c);
+    let mut utf8_buf = [0u8; 8];
+    let s = if synthesized {
+        " "
+    } else {
+        cell.c.encode_utf8(&mut utf8_buf)
+    };
+    let (fg, bg) = cell_colors::<D::Color>(&cell);
+    let char_size = fonts.char_size();
+    let wide_font = if cell.flags.contains(Flags::WIDE_CHAR) {
+        fonts.wide().filter(|font| has_glyph(font, cell.c))
+    } else {
+        None
+    };
+    let font = match wide_font {
+        Some(font) => font,
+        None => style_font(fonts, cell.flags),
+    };
+    draw_text(target, &font, point, s, &cell);
+    if synthesized {
+        let rect = Rectangle::new(point, char_size);
+        box_drawing::draw(target, cell.c, rect, fg);
+    }
+    if cell.flags.contains(Flags::WIDE_CHAR) && wide_font.is_none() {
+        // the glyph only covers the first cell, clear the second one
+        let spacer = Rectangle::new(point + Point::new(char_size.width as i32, 0), char_size);
+        target.fill_solid(&spacer, bg).ok();
+    }
+    // overlay zero-width characters with transparent background
+    let style = MonoTextStyleBuilder::new()
+        .font(&font)
+        .text_color(fg)
+        .build();
+    for c in cell.zerowidth().filter(|&c| has_glyph(&font, c)) {
+        let s = c.encode_utf8(&mut utf8_buf);
+        let text = Text::with_text_style(s, point, style, TextStyle::with_baseline(Baseline::Top));
+        text.draw(target).ok();
+    }
+}
+
+/// A [`DrawTarget`] which can copy pixels from one area to another
+///
+/// Frame buffers that can move pixels faster than drawing them
+/// implement it to scroll [`TextOnGraphic`] by copying, see [`TextOnGraphic::with_copy_area`].
+pub trait CopyArea: DrawTarget {
+    /// Copy pixels in `area` to the area of the same size at `to`. The areas may overlap.
+    fn copy_area(&mut self, area: &Rectangle, to: Point) -> Result<(), Self::Error>;
+}
 
 /// A [`TextBuffer`] on top of a frame buffer
 ///
 /// The internal use [`embedded_graphics`] crate to render fonts to pixels.
+/// The fonts are given by a [`MonoFontSet`], which is [`Font9x18`] by default.
 ///
-/// The underlying frame buffer needs to implement `DrawTarget<Color = Rgb888>` trait
-/// to draw pixels in RGB format.
-pub struct TextOnGraphic<D>
+/// The underlying frame buffer can use any color type converted from [`Rgb888`],
+/// including monochrome [`BinaryColor`](embedded_graphics::pixelcolor::BinaryColor).
+///
+/// Text can't be read back from the frame buffer. Unless it is created
+/// [`with_copy_area`](Self::with_copy_area), scrolling only redraws the exposed rows,
+/// so it should be wrapped in a [`TextBufferCache`](crate::TextBufferCache) which rewrites the others.
+pub struct TextOnGraphic<D, F = Font9x18>
 where
     D: DrawTarget,
 {
     width: u32,
     height: u32,
     graphic: D,
+    font: F,
+    /// Copy pixels of the frame buffer, if it implements [`CopyArea`]
+    copy_area: Option<fn(&mut D, &Rectangle, Point)>,
 }
 
 impl<D> TextOnGraphic<D>
@@ -33,26 +186,65 @@ where
 {
     /// Create a new text buffer on graphic.
     pub fn new(graphic: D, width: u32, height: u32) -> Self {
+        Self::with_font(graphic, width, height, Font9x18)
+    }
+}
+
+impl<D, F> TextOnGraphic<D, F>
+where
+    D: DrawTarget,
+    F: MonoFontSet,
+{
+    /// Create a new text buffer on graphic, rendering text with `font`.
+    pub fn with_font(graphic: D, width: u32, height: u32, font: F) -> Self {
         TextOnGraphic {
             width,
             height,
             graphic,
+            font,
+            copy_area: None,
         }
     }
+
+    /// Cell at pixel `point` as `(row, col)`, or `None` if it is out of the text
+    pub fn cell_at(&self, point: Point) -> Option<(usize, usize)> {
+        let char_size = self.font.char_size();
+        let col = u32::try_from(point.x).ok()? / char_size.width;
+        let row = u32::try_from(point.y).ok()? / char_size.height;
+        let (row, col) = (row as usize, col as usize);
+        let rows = (self.height / char_size.height) as usize;
+        let columns = (self.width / char_size.width) as usize;
+        Some((row, col)).filter(|_| row < rows && col < columns)
+    }
 }
 
-impl<D> TextBuffer for TextOnGraphic<D>
+impl<D, F> TextOnGraphic<D, F>
 where
-    D: DrawTarget<Color = Rgb888>,
+    D: CopyArea,
+{
+    /// Scroll by copying pixels with [`CopyArea`], instead of redrawing the scrolled cells.
+    pub fn with_copy_area(mut self) -> Self {
+        self.copy_area = Some(|graphic, area, to| {
+            graphic.copy_area(area, to).ok();
+        });
+        self
+    }
+}
+
+impl<D, F> TextBuffer for TextOnGraphic<D, F>
+where
+    D: DrawTarget,
+    D::Color: From<Rgb888>,
+    F: MonoFontSet,
 {
     #[inline]
     fn width(&self) -> usize {
-        (self.width / CHAR_SIZE.width) as usize
+        (self.width / self.font.char_size().width) as usize
     }
 
     #[inline]
     fn height(&self) -> usize {
-        (self.height / CHAR_SIZE.height) as usize
+        (self.height / self.font.char_size().height) as usize
     }
 
     fn read(&self, _row: usize, _col: usize) -> Cell {
@@ -64,36 +256,295 @@ where
         if row >= self.height() || col >= self.width() {
             return;
         }
-        let mut utf8_buf = [0u8; 8];
-        let s = cell.c.encode_utf8(&mut utf8_buf);
-        let (fg, bg) = if cell.flags.contains(Flags::INVERSE) {
-            (cell.bg, cell.fg)
+        let char_size = self.font.char_size();
+        let point = cell_point(char_size, row, col);
+        if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+            // the spacer holds the wide character before it, redraw its second half
+            let mut wide = cell;
+            wide.flags.remove(Flags::WIDE_CHAR_SPACER);
+            wide.flags.insert(Flags::WIDE_CHAR);
+            let rect = Rectangle::new(point, char_size);
+            let point = point - Point::new(char_size.width as i32, 0);
+            draw_cell(&mut self.graphic.clipped(&rect), &self.font, point, wide);
+            return;
+        }
+        draw_cell(&mut self.graphic, &self.font, point, cell);
+    }
+
+    fn write_run(&mut self, row: usize, col: usize, cells: &[Cell]) {
+        if row >= self.height() || col >= self.width() {
+            return;
+        }
+        let cells = &cells[..min(cells.len(), self.width() - col)];
+        let char_size = self.font.char_size();
+        let mut text = String::with_capacity(cells.len());
+        let mut start = 0;
+        while start < cells.len() {
+            let first = cells[start];
+            if !is_plain(&first) {
+                self.write(row, col + start, first);
+                start += 1;
+                continue;
+            }
+            // draw cells with the same attributes as one text
+            let same = |cell: &&Cell| {
+                is_plain(cell)
+                    && (cell.fg, cell.bg, cell.flags) == (first.fg, first.bg, first.flags)
+            };
+            text.clear();
+            text.extend(cells[start..].iter().take_while(same).map(|cell| cell.c));
+            let font = style_font(&self.font, first.flags);
+            let point = cell_point(char_size, row, col + start);
+            draw_text(&mut self.graphic, &font, point, &text, &first);
+            start += text.chars().count();
+        }
+    }
+
+    fn can_scroll(&self) -> bool {
+        self.copy_area.is_some()
+    }
+
+    fn scroll_region(&mut self, top: usize, bottom: usize, n: isize, fill: Cell) {
+        let bottom = min(bottom, self.height());
+        if top >= bottom || n == 0 {
+            return;
+        }
+        let lines = min(n.unsigned_abs(), bottom - top);
+        let (src, dst, exposed) = if n > 0 {
+            (top + lines, top, bottom - lines..bottom)
         } else {
-            (cell.fg, cell.bg)
+            (top, top + lines, top..top + lines)
         };
-        let mut style = MonoTextStyleBuilder::new()
-            .text_color(fg.to_rgb())
-            .background_color(bg.to_rgb());
-        if cell.flags.contains(Flags::BOLD) {
-            style = style.font(&FONT_BOLD);
-        } else {
-            style = style.font(&FONT);
+        // without a copy area, the pixels of the scrolled rows can't be moved
+        if let Some(copy_area) = self.copy_area.filter(|_| lines < bottom - top) {
+            let char_size = self.font.char_size();
+            let size = Size::new(
+                char_size.width * self.width() as u32,
+                char_size.height * (bottom - top - lines) as u32,
+            );
+            let area = Rectangle::new(cell_point(char_size, src, 0), size);
+            copy_area(&mut self.graphic, &area, cell_point(char_size, dst, 0));
         }
-        if cell.flags.contains(Flags::STRIKEOUT) {
-            style = style.strikethrough();
+        for row in exposed {
+            for col in 0..self.width() {
+                self.write(row, col, fill);
+            }
         }
-        if cell.flags.contains(Flags::UNDERLINE) {
-            style = style.underline();
+    }
+
+    fn write_with_cursor(&mut self, row: usize, col: usize, cell: Cell, shape: CursorShape) {
+        let mut cell = cell;
+        if shape == CursorShape::Block {
+            cell.flags.toggle(Flags::INVERSE);
         }
-        let text = Text::with_text_style(
-            s,
-            Point::new(
-                col as i32 * CHAR_SIZE.width as i32,
-                row as i32 * CHAR_SIZE.height as i32,
+        self.write(row, col, cell);
+        if row >= self.height() || col >= self.width() {
+            return;
+        }
+        let (fg, _) = cell_colors::<D::Color>(&cell);
+        let char_size = self.font.char_size();
+        let point = cell_point(char_size, row, col);
+        let rect = match shape {
+            CursorShape::Block => return,
+            CursorShape::Underline => Rectangle::new(
+                point + Point::new(0, (char_size.height - CURSOR_THICKNESS) as i32),
+                Size::new(char_size.width, CURSOR_THICKNESS),
             ),
-            style.build(),
-            TextStyle::with_baseline(Baseline::Top),
-        );
-        text.draw(&mut self.graphic).ok();
+            CursorShape::Bar => {
+                Rectangle::new(point, Size::new(CURSOR_THICKNESS, char_size.height))
+            }
+        };
+        self.graphic.fill_solid(&rect, fg).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Console, MonoFonts, TextBufferCache};
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+    use core::fmt::Write;
+    use embedded_graphics::mono_font::ascii::FONT_6X10;
+    use embedded_graphics::prelude::{OriginDimensions, Pixel, PointsIter};
+
+    /// Frame buffer in memory
+    struct Pixels {
+        size: Size,
+        data: Vec<Rgb888>,
+    }
+
+    impl DrawTarget for Pixels {
+        type Color = Rgb888;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            let bounds = Rectangle::new(Point::zero(), self.size);
+            for Pixel(p, color) in pixels.into_iter().filter(|Pixel(p, _)| bounds.contains(*p)) {
+                self.data[(p.y as u32 * self.size.width + p.x as u32) as usize] = color;
+            }
+            Ok(())
+        }
+    }
+
+    impl OriginDimensions for Pixels {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    type TestConsole = Console<TextBufferCache<TextOnGraphic<Pixels, MonoFonts<'static>>>>;
+
+    /// Console of 10x2 cells with 6x10 font
+    fn console() -> TestConsole {
+        let pixels = Pixels {
+            size: Size::new(60, 20),
+            data: vec![Rgb888::BLACK; 60 * 20],
+        };
+        let buffer = TextOnGraphic::with_font(pixels, 60, 20, MonoFonts::new(FONT_6X10));
+        Console::on_cached_text_buffer(buffer)
+    }
+
+    /// Colors of the pixels in cell at `(row, col)`
+    fn cell_pixels(console: &TestConsole, row: usize, col: usize) -> Vec<Rgb888> {
+        let pixels = &console.buffer().inner().graphic;
+        let rect = Rectangle::new(cell_point(Size::new(6, 10), row, col), Size::new(6, 10));
+        rect.points()
+            .map(|p| pixels.data[(p.y as u32 * pixels.size.width + p.x as u32) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn cursor_on_wide_char_spacer() {
+        let black = Rgb888::BLACK;
+        let mut console = console();
+        write!(console, "\x1b[4 q\u{4e2d}\x1b[D").unwrap();
+        assert!(cell_pixels(&console, 0, 1).iter().any(|&c| c != black));
+        write!(console, "\x1b[2;1H").unwrap();
+        assert!(cell_pixels(&console, 0, 1).iter().all(|&c| c == black));
+
+        write!(console, "\x1b[2 q\x1b[1;2H").unwrap();
+        assert!(cell_pixels(&console, 0, 1).iter().all(|&c| c != black));
+        write!(console, "\x1b[2;1H").unwrap();
+        assert!(cell_pixels(&console, 0, 1).iter().all(|&c| c == black));
+    }
+
+    #[test]
+    fn frame_buffer_smaller_than_a_cell() {
+        let pixels = Pixels {
+            size: Size::new(5, 5),
+            data: vec![Rgb888::BLACK; 5 * 5],
+        };
+        let console = Console::on_frame_buffer_with_font(pixels, MonoFonts::new(FONT_6X10));
+        assert_eq!((console.rows(), console.columns()), (0, 0));
+    }
+
+    #[test]
+    fn scroll_without_copy_area() {
+        let pixels = Pixels {
+            size: Size::new(60, 20),
+            data: vec![Rgb888::BLACK; 60 * 20],
+        };
+        let mut buffer = TextOnGraphic::with_font(pixels, 60, 20, MonoFonts::new(FONT_6X10));
+        let mut cell = Cell::default();
+        cell.c = 'X';
+        buffer.write(1, 0, cell);
+        buffer.scroll_region(0, 2, 1, Cell::default());
+        let rect = Rectangle::new(Point::new(0, 10), Size::new(6, 10));
+        let data = &buffer.graphic.data;
+        assert!(rect
+            .points()
+            .all(|p| data[(p.y * 60 + p.x) as usize] == Rgb888::BLACK));
+    }
+
+    #[test]
+    fn write_run_matches_cells() {
+        let buffer = || {
+            let pixels = Pixels {
+                size: Size::new(60, 20),
+                data: vec![Rgb888::BLACK; 60 * 20],
+            };
+            TextOnGraphic::with_font(pixels, 60, 20, MonoFonts::new(FONT_6X10))
+        };
+        let cell = |c: char, flags: Flags| {
+            let mut cell = Cell::default();
+            cell.c = c;
+            cell.flags = flags;
+            cell
+        };
+        let mut combined = cell('e', Flags::empty());
+        combined.push_zerowidth('\u{301}');
+        let cells = [
+            cell('a', Flags::empty()),
+            cell('b', Flags::empty()),
+            cell('c', Flags::UNDERLINE),
+            cell('d', Flags::UNDERLINE | Flags::INVERSE),
+            cell('\u{2500}', Flags::empty()),
+            combined,
+            cell('\u{4e2d}', Flags::WIDE_CHAR),
+            cell('\u{4e2d}', Flags::WIDE_CHAR_SPACER),
+            cell('x', Flags::empty()),
+            cell('y', Flags::empty()),
+            cell('z', Flags::empty()),
+        ];
+        let mut run = buffer();
+        run.write_run(1, 0, &cells);
+        let mut single = buffer();
+        for (col, &cell) in cells.iter().take(10).enumerate() {
+            single.write(1, col, cell);
+        }
+        assert!(run.graphic.data == single.graphic.data);
+    }
+
+    #[test]
+    fn alternate_screen_without_cache() {
+        let pixels = Pixels {
+            size: Size::new(60, 20),
+            data: vec![Rgb888::BLACK; 60 * 20],
+        };
+        let buffer = TextOnGraphic::with_font(pixels, 60, 20, MonoFonts::new(FONT_6X10));
+        let mut uncached = Console::on_text_buffer(buffer);
+        uncached.write_str("\x1b[?1049h\x1b[?1049l").unwrap();
+    }
+
+    #[test]
+    fn text_without_cache() {
+        let pixels = Pixels {
+            size: Size::new(60, 20),
+            data: vec![Rgb888::BLACK; 60 * 20],
+        };
+        let buffer = TextOnGraphic::with_font(pixels, 60, 20, MonoFonts::new(FONT_6X10));
+        let mut uncached = Console::on_text_buffer(buffer);
+        uncached.write_str("a").unwrap();
+    }
+
+    #[test]
+    fn cell_colors_on_binary_color() {
+        use crate::color::Color;
+        use embedded_graphics::pixelcolor::BinaryColor;
+
+        let cell = |fg: u8, bg: u8, flags: Flags| {
+            let mut cell = Cell::default();
+            cell.fg = Color::Spec(Rgb888::new(fg, fg, fg));
+            cell.bg = Color::Spec(Rgb888::new(bg, bg, bg));
+            cell.flags = flags;
+            cell
+        };
+        let colors = |cell: Cell| cell_colors::<BinaryColor>(&cell);
+        let (on, off) = (BinaryColor::On, BinaryColor::Off);
+        // distinct colors are kept
+        assert_eq!(colors(cell(255, 0, Flags::empty())), (on, off));
+        assert_eq!(colors(cell(255, 0, Flags::INVERSE)), (off, on));
+        // dark colors that quantize to the same color
+        assert_eq!(colors(cell(60, 20, Flags::empty())), (on, off));
+        assert_eq!(colors(cell(60, 20, Flags::INVERSE)), (off, on));
+        // light colors that quantize to the same color
+        assert_eq!(colors(cell(200, 250, Flags::empty())), (off, on));
+        assert_eq!(colors(cell(200, 250, Flags::INVERSE)), (on, off));
+        // the same color stays invisible
+        assert_eq!(colors(cell(60, 60, Flags::empty())), (off, off));
     }
 }
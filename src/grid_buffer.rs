@@ -0,0 +1,241 @@
+use crate::ansi::CursorShape;
+use crate::cell::{Cell, Flags};
+use crate::color::Color;
+use crate::text_buffer::TextBuffer;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::fmt::Write;
+use embedded_graphics::prelude::RgbColor;
+
+/// Flags shown by [`GridBuffer::text_with_attributes`], with their names
+const STYLES: [(Flags, &str); 9] = [
+    (Flags::BOLD, "bold"),
+    (Flags::DIM, "dim"),
+    (Flags::ITALIC, "italic"),
+    (Flags::UNDERLINE, "underline"),
+    (Flags::DOUBLE_UNDERLINE, "double-underline"),
+    (Flags::BLINK, "blink"),
+    (Flags::INVERSE, "inverse"),
+    (Flags::HIDDEN, "hidden"),
+    (Flags::STRIKEOUT, "strikeout"),
+];
+
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+/// A [`TextBuffer`] storing cells in memory
+///
+/// It is useful for running a [`Console`](crate::Console) headless, e.g. in snapshot tests
+/// comparing [`text`](Self::text) or [`text_with_attributes`](Self::text_with_attributes).
+/// The cursor is not drawn on the cells, but kept as [`cursor`](Self::cursor).
+pub struct GridBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    cursor: Option<(usize, usize)>,
+}
+
+impl GridBuffer {
+    /// Create a blank buffer of `width` columns and `height` rows
+    pub fn new(width: usize, height: usize) -> Self {
+        GridBuffer {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+            cursor: None,
+        }
+    }
+
+    /// Position of the cursor as `(row, col)`, or `None` if it is hidden
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    /// Characters of `row`, including zero-width characters attached to them
+    ///
+    /// Trailing blank cells are omitted.
+    pub fn row_text(&self, row: usize) -> String {
+        let mut text = String::new();
+        for cell in self.row(row, |cell| cell.c == ' ') {
+            push_char(&mut text, cell);
+        }
+        text
+    }
+
+    /// Characters of the screen, one line per row
+    ///
+    /// Trailing blank cells of each row are omitted.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for row in 0..self.height {
+            text += &self.row_text(row);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Characters of the screen, one line per row, with attributes
+    ///
+    /// Each row starts with the default attributes. Wherever they change, they are written
+    /// in braces as the colors and styles differing from the default.
+    /// For example, `{fg:red bold}` or `{}` for the default.
+    /// Colors are written as names, palette indices or `#rrggbb`.
+    ///
+    /// Trailing blank cells of each row are omitted if they have the default attributes.
+    pub fn text_with_attributes(&self) -> String {
+        let default = Attributes::of(&Cell::default());
+        let mut text = String::new();
+        for row in 0..self.height {
+            let mut current = default;
+            let cells = self.row(row, |cell| cell.c == ' ' && Attributes::of(cell) == default);
+            for cell in cells {
+                let attributes = Attributes::of(cell);
+                if attributes != current {
+                    current = attributes;
+                    attributes.write(&mut text);
+                }
+                push_char(&mut text, cell);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Cells of `row`, excluding trailing ones that are `blank`
+    fn row(&self, row: usize, blank: impl Fn(&Cell) -> bool) -> &[Cell] {
+        let cells = &self.cells[row * self.width..(row + 1) * self.width];
+        let len = cells
+            .iter()
+            .rposition(|cell| !blank(cell))
+            .map_or(0, |i| i + 1);
+        &cells[..len]
+    }
+}
+
+/// Push the characters of `cell` to `text`
+fn push_char(text: &mut String, cell: &Cell) {
+    if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+        return;
+    }
+    text.push(cell.c);
+    text.extend(cell.zerowidth());
+}
+
+/// Colors and styles of a cell
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Attributes {
+    fg: Color,
+    bg: Color,
+    flags: Flags,
+}
+
+impl Attributes {
+    fn of(cell: &Cell) -> Self {
+        let styles = STYLES
+            .iter()
+            .fold(Flags::empty(), |flags, &(flag, _)| flags | flag);
+        Attributes {
+            fg: cell.fg,
+            bg: cell.bg,
+            flags: cell.flags & styles,
+        }
+    }
+
+    /// Write the attributes differing from the default to `text`
+    fn write(&self, text: &mut String) {
+        let default = Cell::default();
+        let mut items = Vec::new();
+        if self.fg != default.fg {
+            items.push(format!("fg:{}", color_name(self.fg)));
+        }
+        if self.bg != default.bg {
+            items.push(format!("bg:{}", color_name(self.bg)));
+        }
+        for &(flag, name) in STYLES.iter() {
+            if self.flags.contains(flag) {
+                items.push(String::from(name));
+            }
+        }
+        write!(text, "{{{}}}", items.join(" ")).unwrap();
+    }
+}
+
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Named(name) => String::from(COLOR_NAMES[name as usize]),
+        Color::Indexed(index) => format!("{}", index),
+        Color::Spec(rgb) => format!("#{:02x}{:02x}{:02x}", rgb.r(), rgb.g(), rgb.b()),
+    }
+}
+
+impl TextBuffer for GridBuffer {
+    #[inline]
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    fn read(&self, row: usize, col: usize) -> Cell {
+        self.cells[row * self.width + col]
+    }
+
+    #[inline]
+    fn write(&mut self, row: usize, col: usize, cell: Cell) {
+        self.cells[row * self.width + col] = cell;
+    }
+
+    fn write_with_cursor(&mut self, row: usize, col: usize, cell: Cell, _shape: CursorShape) {
+        self.write(row, col, cell);
+    }
+
+    fn set_cursor(&mut self, pos: Option<(usize, usize)>, _shape: CursorShape) {
+        self.cursor = pos;
+    }
+
+    fn scroll_region(&mut self, top: usize, bottom: usize, n: isize, fill: Cell) {
+        let bottom = min(bottom, self.height);
+        if top >= bottom || n == 0 {
+            return;
+        }
+        let region = &mut self.cells[top * self.width..bottom * self.width];
+        let len = min(n.unsigned_abs(), bottom - top) * self.width;
+        if n > 0 {
+            region.rotate_left(len);
+            let start = region.len() - len;
+            region[start..].fill(fill);
+        } else {
+            region.rotate_right(len);
+            region[..len].fill(fill);
+        }
+    }
+
+    fn can_scroll(&self) -> bool {
+        true
+    }
+
+    fn clear(&mut self, cell: Cell) {
+        self.cells.fill(cell);
+    }
+}
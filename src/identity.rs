@@ -0,0 +1,95 @@
+use alloc::collections::VecDeque;
+use core::fmt::Write;
+
+/// Identity of the terminal reported to programs asking for device attributes
+///
+/// The default is a VT220-class terminal with ANSI colors, which is what
+/// the console implements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalIdentity {
+    /// Conformance level of the primary attributes (DA1), e.g. 62 for VT220 or 64 for VT420
+    pub conformance_level: u16,
+    /// Extensions following the conformance level in the primary attributes,
+    /// e.g. 22 for ANSI colors
+    pub features: &'static [u16],
+    /// Terminal type of the secondary attributes (DA2), e.g. 1 for VT220
+    pub terminal_type: u16,
+    /// Firmware version of the secondary attributes
+    pub version: u16,
+    /// Unit ID of the tertiary attributes (DA3), sent as 8 hexadecimal digits
+    pub unit_id: u32,
+}
+
+impl Default for TerminalIdentity {
+    fn default() -> Self {
+        TerminalIdentity {
+            conformance_level: 62,
+            features: &[22],
+            terminal_type: 1,
+            version: 0,
+            unit_id: 0,
+        }
+    }
+}
+
+impl TerminalIdentity {
+    /// Write the device attributes asked for by `intermediate` to `out`
+    ///
+    /// `None` is for the primary attributes, `Some('>')` for the secondary
+    /// and `Some('=')` for the tertiary.
+    pub(crate) fn encode(&self, intermediate: Option<char>, out: &mut VecDeque<u8>) {
+        let mut report = alloc::string::String::new();
+        match intermediate {
+            None => {
+                write!(report, "\x1b[?{}", self.conformance_level).unwrap();
+                for feature in self.features {
+                    write!(report, ";{}", feature).unwrap();
+                }
+                report.push('c');
+            }
+            Some('>') => {
+                let (kind, version) = (self.terminal_type, self.version);
+                write!(report, "\x1b[>{};{};0c", kind, version).unwrap();
+            }
+            Some('=') => write!(report, "\x1bP!|{:08X}\x1b\\", self.unit_id).unwrap(),
+            Some(c) => debug!("unknown device attributes query: {:?}", c),
+        }
+        out.extend(report.bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Reply of `identity` to the query of `intermediate`
+    fn reply(identity: &TerminalIdentity, intermediate: Option<char>) -> Vec<u8> {
+        let mut out = VecDeque::new();
+        identity.encode(intermediate, &mut out);
+        out.into()
+    }
+
+    #[test]
+    fn default_identity() {
+        let identity = TerminalIdentity::default();
+        assert_eq!(reply(&identity, None), b"\x1b[?62;22c");
+        assert_eq!(reply(&identity, Some('>')), b"\x1b[>1;0;0c");
+        assert_eq!(reply(&identity, Some('=')), b"\x1bP!|00000000\x1b\\");
+        assert_eq!(reply(&identity, Some('?')), b"");
+    }
+
+    #[test]
+    fn custom_identity() {
+        let identity = TerminalIdentity {
+            conformance_level: 64,
+            features: &[1, 22],
+            terminal_type: 41,
+            version: 330,
+            unit_id: 0xc0ffee,
+        };
+        assert_eq!(reply(&identity, None), b"\x1b[?64;1;22c");
+        assert_eq!(reply(&identity, Some('>')), b"\x1b[>41;330;0c");
+        assert_eq!(reply(&identity, Some('=')), b"\x1bP!|00C0FFEE\x1b\\");
+    }
+}
@@ -0,0 +1,292 @@
+use alloc::collections::VecDeque;
+
+/// A key pressed on the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A key producing a character, with Shift and the layout already applied
+    Char(char),
+    /// Enter or Return
+    Enter,
+    /// Tab
+    Tab,
+    /// Backspace
+    Backspace,
+    /// Escape
+    Escape,
+    /// Up arrow
+    Up,
+    /// Down arrow
+    Down,
+    /// Left arrow
+    Left,
+    /// Right arrow
+    Right,
+    /// Home
+    Home,
+    /// End
+    End,
+    /// Insert
+    Insert,
+    /// Delete
+    Delete,
+    /// Page Up
+    PageUp,
+    /// Page Down
+    PageDown,
+    /// Function key F1 to F12
+    F(u8),
+    /// A key on the numeric keypad producing one of `0`-`9`, `.`, `+`, `-`, `*`, `/` and `=`
+    Keypad(char),
+    /// Enter on the numeric keypad
+    KeypadEnter,
+}
+
+bitflags::bitflags! {
+    /// Modifier keys held with a [`Key`]
+    ///
+    /// The bits match the modifier parameter of xterm minus one.
+    pub struct Modifiers: u8 {
+        /// Shift
+        const SHIFT = 0b0001;
+        /// Alt
+        const ALT = 0b0010;
+        /// Control
+        const CONTROL = 0b0100;
+        /// Meta
+        const META = 0b1000;
+    }
+}
+
+/// Write the bytes xterm sends for `key` to `out`
+///
+/// `cursor_keys` and `keypad` are whether the cursor keys (DECCKM)
+/// and the keypad (DECKPAM) are in application mode.
+pub fn encode(
+    key: Key,
+    modifiers: Modifiers,
+    cursor_keys: bool,
+    keypad: bool,
+    out: &mut VecDeque<u8>,
+) {
+    let alt = modifiers.intersects(Modifiers::ALT | Modifiers::META);
+    let control = modifiers.contains(Modifiers::CONTROL);
+    match key {
+        Key::Char(c) if control => match control_char(c) {
+            Some(byte) => send_char(out, alt, byte as char),
+            None => send_char(out, alt, c),
+        },
+        Key::Char(c) => send_char(out, alt, c),
+        Key::Enter => send_char(out, alt, '\r'),
+        Key::Tab if modifiers.contains(Modifiers::SHIFT) => out.extend(*b"\x1b[Z"),
+        Key::Tab => send_char(out, alt, '\t'),
+        Key::Backspace if control => send_char(out, alt, '\x08'),
+        Key::Backspace => send_char(out, alt, '\x7f'),
+        Key::Escape => send_char(out, alt, '\x1b'),
+        Key::Up => cursor(out, cursor_keys, modifiers, b'A'),
+        Key::Down => cursor(out, cursor_keys, modifiers, b'B'),
+        Key::Right => cursor(out, cursor_keys, modifiers, b'C'),
+        Key::Left => cursor(out, cursor_keys, modifiers, b'D'),
+        Key::Home => cursor(out, cursor_keys, modifiers, b'H'),
+        Key::End => cursor(out, cursor_keys, modifiers, b'F'),
+        Key::Insert => tilde(out, 2, modifiers),
+        Key::Delete => tilde(out, 3, modifiers),
+        Key::PageUp => tilde(out, 5, modifiers),
+        Key::PageDown => tilde(out, 6, modifiers),
+        Key::F(n @ 1..=4) => {
+            let byte = b'P' + n - 1;
+            if modifiers.is_empty() {
+                out.extend([0x1b, b'O', byte]);
+            } else {
+                csi(out, 1, modifiers, byte);
+            }
+        }
+        Key::F(n @ 5..=12) => {
+            const CODES: [u8; 8] = [15, 17, 18, 19, 20, 21, 23, 24];
+            tilde(out, CODES[n as usize - 5], modifiers);
+        }
+        Key::F(n) => debug!("no sequence for F{}", n),
+        Key::Keypad(c) if keypad => match keypad_final(c) {
+            Some(byte) => ss3(out, modifiers, byte),
+            None => send_char(out, alt, c),
+        },
+        Key::Keypad(c) => send_char(out, alt, c),
+        Key::KeypadEnter if keypad => ss3(out, modifiers, b'M'),
+        Key::KeypadEnter => send_char(out, alt, '\r'),
+    }
+}
+
+/// Send `c`, prefixed by ESC if Alt is held
+fn send_char(out: &mut VecDeque<u8>, alt: bool, c: char) {
+    if alt {
+        out.push_back(0x1b);
+    }
+    let mut bytes = [0; 4];
+    out.extend(c.encode_utf8(&mut bytes).bytes());
+}
+
+/// Control character sent for `c` with Control held, as xterm does
+fn control_char(c: char) -> Option<u8> {
+    Some(match c {
+        '@' | ' ' | '2' => 0x00,
+        'a'..='z' => c as u8 - b'a' + 1,
+        '['..='_' | 'A'..='Z' => c as u8 & 0x1f,
+        '3' => 0x1b,
+        '4' => 0x1c,
+        '5' => 0x1d,
+        '6' => 0x1e,
+        '7' | '/' => 0x1f,
+        '8' | '?' => 0x7f,
+        _ => return None,
+    })
+}
+
+/// Final byte of the application keypad sequence for `c`
+fn keypad_final(c: char) -> Option<u8> {
+    Some(match c {
+        '0'..='9' => c as u8 - b'0' + b'p',
+        '.' => b'n',
+        '+' => b'k',
+        '-' => b'm',
+        '*' => b'j',
+        '/' => b'o',
+        '=' => b'X',
+        _ => return None,
+    })
+}
+
+/// Send `ESC [ param ; modifiers final`, omitting default parameters
+fn csi(out: &mut VecDeque<u8>, param: u8, modifiers: Modifiers, byte: u8) {
+    out.extend([0x1b, b'[']);
+    if !modifiers.is_empty() {
+        decimal(out, param);
+        out.push_back(b';');
+        decimal(out, modifiers.bits() + 1);
+    } else if param != 1 {
+        decimal(out, param);
+    }
+    out.push_back(byte);
+}
+
+/// Send `ESC O final`, with the modifier parameter before `final` if any
+fn ss3(out: &mut VecDeque<u8>, modifiers: Modifiers, byte: u8) {
+    out.extend([0x1b, b'O']);
+    if !modifiers.is_empty() {
+        decimal(out, modifiers.bits() + 1);
+    }
+    out.push_back(byte);
+}
+
+/// Send a cursor key, which is `ESC O final` in application mode without modifiers
+fn cursor(out: &mut VecDeque<u8>, application: bool, modifiers: Modifiers, byte: u8) {
+    if application && modifiers.is_empty() {
+        ss3(out, modifiers, byte);
+    } else {
+        csi(out, 1, modifiers, byte);
+    }
+}
+
+/// Send `ESC [ code ~` with modifiers
+fn tilde(out: &mut VecDeque<u8>, code: u8, modifiers: Modifiers) {
+    out.extend([0x1b, b'[']);
+    decimal(out, code);
+    if !modifiers.is_empty() {
+        out.push_back(b';');
+        decimal(out, modifiers.bits() + 1);
+    }
+    out.push_back(b'~');
+}
+
+fn decimal(out: &mut VecDeque<u8>, n: u8) {
+    if n >= 10 {
+        out.push_back(b'0' + n / 10);
+    }
+    out.push_back(b'0' + n % 10);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const SHIFT: Modifiers = Modifiers::SHIFT;
+    const ALT: Modifiers = Modifiers::ALT;
+    const CONTROL: Modifiers = Modifiers::CONTROL;
+    const NONE: Modifiers = Modifiers::empty();
+
+    /// Bytes sent for `key` in normal mode
+    fn normal(key: Key, modifiers: Modifiers) -> Vec<u8> {
+        let mut out = VecDeque::new();
+        encode(key, modifiers, false, false, &mut out);
+        out.into()
+    }
+
+    /// Bytes sent for `key` with cursor keys and keypad in application mode
+    fn application(key: Key, modifiers: Modifiers) -> Vec<u8> {
+        let mut out = VecDeque::new();
+        encode(key, modifiers, true, true, &mut out);
+        out.into()
+    }
+
+    #[test]
+    fn characters() {
+        assert_eq!(normal(Key::Char('a'), NONE), b"a");
+        assert_eq!(normal(Key::Char('\u{e9}'), NONE), "\u{e9}".as_bytes());
+        assert_eq!(normal(Key::Char('a'), CONTROL), b"\x01");
+        assert_eq!(normal(Key::Char('A'), CONTROL), b"\x01");
+        assert_eq!(normal(Key::Char('2'), CONTROL), b"\x00");
+        assert_eq!(normal(Key::Char('?'), CONTROL), b"\x7f");
+        assert_eq!(normal(Key::Char('a'), ALT), b"\x1ba");
+        assert_eq!(normal(Key::Char('['), ALT | CONTROL), b"\x1b\x1b");
+        assert_eq!(normal(Key::Char('\u{e9}'), CONTROL), "\u{e9}".as_bytes());
+    }
+
+    #[test]
+    fn editing_keys() {
+        assert_eq!(normal(Key::Enter, NONE), b"\r");
+        assert_eq!(normal(Key::Enter, ALT), b"\x1b\r");
+        assert_eq!(normal(Key::Tab, NONE), b"\t");
+        assert_eq!(normal(Key::Tab, SHIFT), b"\x1b[Z");
+        assert_eq!(normal(Key::Backspace, NONE), b"\x7f");
+        assert_eq!(normal(Key::Backspace, CONTROL), b"\x08");
+        assert_eq!(normal(Key::Escape, NONE), b"\x1b");
+        assert_eq!(normal(Key::Insert, NONE), b"\x1b[2~");
+        assert_eq!(normal(Key::Delete, NONE), b"\x1b[3~");
+        assert_eq!(normal(Key::PageUp, SHIFT), b"\x1b[5;2~");
+        assert_eq!(application(Key::PageDown, NONE), b"\x1b[6~");
+    }
+
+    #[test]
+    fn cursor_keys() {
+        assert_eq!(normal(Key::Up, NONE), b"\x1b[A");
+        assert_eq!(normal(Key::Home, NONE), b"\x1b[H");
+        assert_eq!(application(Key::Up, NONE), b"\x1bOA");
+        assert_eq!(application(Key::End, NONE), b"\x1bOF");
+        assert_eq!(normal(Key::Left, SHIFT), b"\x1b[1;2D");
+        assert_eq!(application(Key::Right, CONTROL), b"\x1b[1;5C");
+        assert_eq!(normal(Key::Down, SHIFT | ALT | CONTROL), b"\x1b[1;8B");
+    }
+
+    #[test]
+    fn function_keys() {
+        assert_eq!(normal(Key::F(1), NONE), b"\x1bOP");
+        assert_eq!(normal(Key::F(4), NONE), b"\x1bOS");
+        assert_eq!(normal(Key::F(1), CONTROL), b"\x1b[1;5P");
+        assert_eq!(normal(Key::F(5), NONE), b"\x1b[15~");
+        assert_eq!(normal(Key::F(6), NONE), b"\x1b[17~");
+        assert_eq!(normal(Key::F(12), NONE), b"\x1b[24~");
+        assert_eq!(normal(Key::F(12), ALT), b"\x1b[24;3~");
+        assert_eq!(normal(Key::F(13), NONE), b"");
+    }
+
+    #[test]
+    fn keypad() {
+        assert_eq!(normal(Key::Keypad('5'), NONE), b"5");
+        assert_eq!(normal(Key::KeypadEnter, NONE), b"\r");
+        assert_eq!(application(Key::Keypad('5'), NONE), b"\x1bOu");
+        assert_eq!(application(Key::Keypad('0'), NONE), b"\x1bOp");
+        assert_eq!(application(Key::Keypad('+'), NONE), b"\x1bOk");
+        assert_eq!(application(Key::Keypad('/'), NONE), b"\x1bOo");
+        assert_eq!(application(Key::Keypad('1'), SHIFT), b"\x1bO2q");
+        assert_eq!(application(Key::KeypadEnter, NONE), b"\x1bOM");
+    }
+}
@@ -27,15 +27,37 @@ extern crate log;
 #[macro_use]
 mod log;
 
+pub use ansi::{CursorShape, CursorStyle};
+pub use bdf::{BdfError, BdfFont};
 pub use console::{Console, ConsoleOnGraphic};
-pub use graphic::TextOnGraphic;
+pub use font::{Font9x18, MonoFontSet, MonoFonts};
+pub use graphic::{CopyArea, TextOnGraphic};
+pub use grid_buffer::GridBuffer;
+pub use identity::TerminalIdentity;
+pub use keyboard::{Key, Modifiers};
+pub use mouse::{MouseButton, MouseEventKind};
+#[cfg(feature = "ps2")]
+pub use ps2::{Layout, Ps2Keyboard, ScancodeSet};
+pub use psf::{PsfError, PsfFont};
 pub use text_buffer::TextBuffer;
-pub use text_buffer_cache::TextBufferCache;
+pub use text_buffer_cache::{TextBufferCache, DEFAULT_HISTORY_SIZE};
+pub use vga::{NoPorts, VgaMemory, VgaPorts, VgaTextBuffer};
 
 mod ansi;
+mod bdf;
+mod box_drawing;
 mod cell;
 mod color;
 mod console;
+mod font;
 mod graphic;
+mod grid_buffer;
+mod identity;
+mod keyboard;
+mod mouse;
+#[cfg(feature = "ps2")]
+mod ps2;
+mod psf;
 mod text_buffer;
 mod text_buffer_cache;
+mod vga;
@@ -0,0 +1,287 @@
+use crate::keyboard::Modifiers;
+use alloc::collections::VecDeque;
+
+/// What happened to the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEventKind {
+    /// A button is pressed, or the wheel is scrolled
+    Press,
+    /// A button is released
+    Release,
+    /// The mouse is moved to another cell
+    Motion,
+}
+
+/// A mouse button, or a direction of the wheel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    /// Left button
+    Left,
+    /// Middle button
+    Middle,
+    /// Right button
+    Right,
+    /// Wheel scrolled up
+    WheelUp,
+    /// Wheel scrolled down
+    WheelDown,
+}
+
+/// Mouse events reported to the program, set by `?9`, `?1000`, `?1002` and `?1003`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseMode {
+    /// No reports
+    Off,
+    /// Presses only
+    X10,
+    /// Presses and releases
+    Normal,
+    /// Presses, releases and motion with a button held
+    ButtonEvent,
+    /// Presses, releases and all motion
+    AnyEvent,
+}
+
+/// Encoding of mouse reports, set by `?1005` and `?1006`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEncoding {
+    /// `ESC [ M` with a byte for the button and each coordinate
+    Default,
+    /// `ESC [ M` with UTF-8 characters for the button and each coordinate
+    Utf8,
+    /// `ESC [ <` with decimal parameters
+    Sgr,
+}
+
+/// Mouse reporting set by the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseProtocol {
+    pub mode: MouseMode,
+    pub encoding: MouseEncoding,
+}
+
+impl Default for MouseProtocol {
+    fn default() -> Self {
+        MouseProtocol {
+            mode: MouseMode::Off,
+            encoding: MouseEncoding::Default,
+        }
+    }
+}
+
+impl MouseProtocol {
+    /// Write the report of a mouse event at `(row, col)` to `out`, if the mode reports it
+    ///
+    /// `button` is `None` for motion without a button held.
+    pub fn encode(
+        &self,
+        kind: MouseEventKind,
+        button: Option<MouseButton>,
+        row: usize,
+        col: usize,
+        modifiers: Modifiers,
+        out: &mut VecDeque<u8>,
+    ) {
+        let MouseProtocol { mode, encoding } = *self;
+        let wheel = matches!(button, Some(MouseButton::WheelUp | MouseButton::WheelDown));
+        let reported = match kind {
+            MouseEventKind::Press => mode != MouseMode::Off && button.is_some(),
+            MouseEventKind::Release => mode != MouseMode::Off && mode != MouseMode::X10 && !wheel,
+            MouseEventKind::Motion => match mode {
+                MouseMode::ButtonEvent => button.is_some(),
+                MouseMode::AnyEvent => true,
+                _ => false,
+            },
+        };
+        if !reported {
+            return;
+        }
+        let mut code = match button {
+            // releases don't tell the button, except in SGR
+            _ if kind == MouseEventKind::Release && encoding != MouseEncoding::Sgr => 3,
+            Some(MouseButton::Left) => 0,
+            Some(MouseButton::Middle) => 1,
+            Some(MouseButton::Right) => 2,
+            Some(MouseButton::WheelUp) => 64,
+            Some(MouseButton::WheelDown) => 65,
+            None => 3,
+        };
+        if kind == MouseEventKind::Motion {
+            code += 32;
+        }
+        if mode != MouseMode::X10 {
+            if modifiers.contains(Modifiers::SHIFT) {
+                code += 4;
+            }
+            if modifiers.intersects(Modifiers::ALT | Modifiers::META) {
+                code += 8;
+            }
+            if modifiers.contains(Modifiers::CONTROL) {
+                code += 16;
+            }
+        }
+        let (x, y) = (col + 1, row + 1);
+        match encoding {
+            MouseEncoding::Default => {
+                // coordinates which don't fit in a byte can't be reported
+                if x > 223 || y > 223 {
+                    return;
+                }
+                out.extend([0x1b, b'[', b'M', 32 + code, 32 + x as u8, 32 + y as u8]);
+            }
+            MouseEncoding::Utf8 => {
+                if x > 2015 || y > 2015 {
+                    return;
+                }
+                out.extend([0x1b, b'[', b'M']);
+                for value in [code as u32, x as u32, y as u32] {
+                    let c = char::from_u32(32 + value).unwrap();
+                    let mut bytes = [0; 4];
+                    out.extend(c.encode_utf8(&mut bytes).bytes());
+                }
+            }
+            MouseEncoding::Sgr => {
+                let end = if kind == MouseEventKind::Release {
+                    'm'
+                } else {
+                    'M'
+                };
+                let report = format!("\x1b[<{};{};{}{}", code, x, y, end);
+                out.extend(report.bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use MouseButton::*;
+    use MouseEventKind::*;
+
+    /// Report of an event at `(row, col)`
+    fn report(
+        mode: MouseMode,
+        encoding: MouseEncoding,
+        kind: MouseEventKind,
+        button: Option<MouseButton>,
+        (row, col): (usize, usize),
+        modifiers: Modifiers,
+    ) -> Vec<u8> {
+        let mut out = VecDeque::new();
+        MouseProtocol { mode, encoding }.encode(kind, button, row, col, modifiers, &mut out);
+        out.into()
+    }
+
+    /// Report in the default encoding of an event at `(row, col)` without modifiers
+    fn x10(
+        mode: MouseMode,
+        kind: MouseEventKind,
+        button: Option<MouseButton>,
+        pos: (usize, usize),
+    ) -> Vec<u8> {
+        report(
+            mode,
+            MouseEncoding::Default,
+            kind,
+            button,
+            pos,
+            Modifiers::empty(),
+        )
+    }
+
+    /// Report in SGR encoding of an event with any motion reported
+    fn sgr(
+        kind: MouseEventKind,
+        button: Option<MouseButton>,
+        pos: (usize, usize),
+        modifiers: Modifiers,
+    ) -> Vec<u8> {
+        report(
+            MouseMode::AnyEvent,
+            MouseEncoding::Sgr,
+            kind,
+            button,
+            pos,
+            modifiers,
+        )
+    }
+
+    #[test]
+    fn modes() {
+        use MouseMode::*;
+        assert_eq!(x10(Off, Press, Some(Left), (0, 0)), b"");
+        assert_eq!(x10(X10, Press, Some(Left), (0, 0)), b"\x1b[M !!");
+        assert_eq!(x10(X10, Release, Some(Left), (0, 0)), b"");
+        assert_eq!(x10(Normal, Release, Some(Left), (1, 2)), b"\x1b[M##\"");
+        assert_eq!(x10(Normal, Motion, Some(Left), (0, 0)), b"");
+        assert_eq!(x10(Normal, Press, Some(WheelUp), (0, 0)), b"\x1b[M`!!");
+        assert_eq!(x10(Normal, Release, Some(WheelUp), (0, 0)), b"");
+        assert_eq!(x10(ButtonEvent, Motion, Some(Left), (0, 0)), b"\x1b[M@!!");
+        assert_eq!(x10(ButtonEvent, Motion, None, (0, 0)), b"");
+        assert_eq!(x10(AnyEvent, Motion, None, (0, 0)), b"\x1b[MC!!");
+    }
+
+    #[test]
+    fn modifiers() {
+        let all = Modifiers::SHIFT | Modifiers::ALT | Modifiers::CONTROL;
+        let pos = (0, 0);
+        let x10 = report(
+            MouseMode::X10,
+            MouseEncoding::Default,
+            Press,
+            Some(Middle),
+            pos,
+            all,
+        );
+        assert_eq!(x10, b"\x1b[M!!!");
+        let normal = report(
+            MouseMode::Normal,
+            MouseEncoding::Default,
+            Press,
+            Some(Middle),
+            pos,
+            all,
+        );
+        assert_eq!(normal, b"\x1b[M=!!");
+    }
+
+    #[test]
+    fn coordinates() {
+        use MouseEncoding::*;
+        let normal = |encoding, pos| {
+            report(
+                MouseMode::Normal,
+                encoding,
+                Press,
+                Some(Left),
+                pos,
+                Modifiers::empty(),
+            )
+        };
+        assert_eq!(normal(Default, (0, 222)), b"\x1b[M \xff!");
+        assert_eq!(normal(Default, (0, 223)), b"");
+        assert_eq!(normal(Utf8, (0, 300)), b"\x1b[M \xc5\x8d!");
+        assert_eq!(normal(Utf8, (0, 2015)), b"");
+        assert_eq!(normal(Sgr, (0, 300)), b"\x1b[<0;301;1M");
+    }
+
+    #[test]
+    fn sgr_reports() {
+        let control = Modifiers::CONTROL;
+        assert_eq!(sgr(Press, Some(Right), (4, 9), control), b"\x1b[<18;10;5M");
+        assert_eq!(
+            sgr(Release, Some(Right), (4, 9), control),
+            b"\x1b[<18;10;5m"
+        );
+        assert_eq!(
+            sgr(Press, Some(WheelDown), (0, 0), Modifiers::empty()),
+            b"\x1b[<65;1;1M"
+        );
+        assert_eq!(
+            sgr(Motion, None, (0, 0), Modifiers::empty()),
+            b"\x1b[<35;1;1M"
+        );
+    }
+}
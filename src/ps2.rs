@@ -0,0 +1,411 @@
+use crate::keyboard::{Key, Modifiers};
+
+/// Scan code set sent by a PS/2 keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScancodeSet {
+    /// Set 1, sent by the i8042 controller when translation is enabled
+    Set1,
+    /// Set 2, the default set of PS/2 keyboards
+    Set2,
+}
+
+/// Keyboard layout mapping keys to characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// US QWERTY
+    Us,
+    /// German QWERTZ
+    De,
+    /// French AZERTY
+    Fr,
+}
+
+/// Characters of the keys with set 1 codes `0x02..=0x0d`, `0x10..=0x1b`, `0x1e..=0x29`,
+/// `0x2b..=0x35` and `0x56`, unshifted, shifted and with AltGr. Spaces are keys without one.
+const US: [&str; 3] = [
+    "1234567890-=qwertyuiop[]asdfghjkl;'`\\zxcvbnm,./\\",
+    "!@#$%^&*()_+QWERTYUIOP{}ASDFGHJKL:\"~|ZXCVBNM<>?|",
+    "",
+];
+const DE: [&str; 3] = [
+    "1234567890ß´qwertzuiopü+asdfghjklöä^#yxcvbnm,.-<",
+    "!\"§$%&/()=?`QWERTZUIOPÜ*ASDFGHJKLÖÄ°'YXCVBNM;:_>",
+    " ²³   {[]}\\ @ €        ~                   µ   |",
+];
+const FR: [&str; 3] = [
+    "&é\"'(-è_çà)=azertyuiop^$qsdfghjklmù²*wxcvbn,;:!<",
+    "1234567890°+AZERTYUIOP¨£QSDFGHJKLM% µWXCVBN?./§>",
+    " ~#{[|`\\^@]}  €        ¤                        ",
+];
+
+/// Set 2 codes of keys, indexed by their set 1 codes
+///
+/// Extended keys have the same codes after `0xe0` in both sets.
+const SET2: [u8; 0x59] = [
+    0x00, 0x76, 0x16, 0x1e, 0x26, 0x25, 0x2e, 0x36, 0x3d, 0x3e, 0x46, 0x45, 0x4e, 0x55, 0x66, 0x0d,
+    0x15, 0x1d, 0x24, 0x2d, 0x2c, 0x35, 0x3c, 0x43, 0x44, 0x4d, 0x54, 0x5b, 0x5a, 0x14, 0x1c, 0x1b,
+    0x23, 0x2b, 0x34, 0x33, 0x3b, 0x42, 0x4b, 0x4c, 0x52, 0x0e, 0x12, 0x5d, 0x1a, 0x22, 0x21, 0x2a,
+    0x32, 0x31, 0x3a, 0x41, 0x49, 0x4a, 0x59, 0x7c, 0x11, 0x29, 0x58, 0x05, 0x06, 0x04, 0x0c, 0x03,
+    0x0b, 0x83, 0x0a, 0x01, 0x09, 0x77, 0x7e, 0x6c, 0x75, 0x7d, 0x7b, 0x6b, 0x73, 0x74, 0x79, 0x69,
+    0x72, 0x7a, 0x70, 0x71, 0x00, 0x00, 0x61, 0x78, 0x07,
+];
+
+/// Bytes a keyboard sends other than scan codes: acknowledge, resend, echo and errors
+const REPLIES: [u8; 5] = [0xfa, 0xfe, 0xee, 0x00, 0xff];
+
+/// Decoder of PS/2 keyboard scan codes into [`Key`]s
+///
+/// It keeps track of modifier keys and Caps Lock, Num Lock and Scroll Lock.
+/// Dead keys are not composed, but produce their own character.
+/// Feed the keys to [`Console::key_event`](crate::Console::key_event)
+/// to send them to the program:
+///
+/// ```ignore
+/// if let Some((key, modifiers)) = keyboard.add_byte(port.read()) {
+///     console.key_event(key, modifiers);
+/// }
+/// ```
+pub struct Ps2Keyboard {
+    set: ScancodeSet,
+    layout: Layout,
+    /// After `0xe0`
+    extended: bool,
+    /// After `0xf0` in set 2
+    release: bool,
+    /// Bytes left of a Pause sequence, which has no break code
+    skip: u8,
+    left_shift: bool,
+    right_shift: bool,
+    left_control: bool,
+    right_control: bool,
+    alt: bool,
+    alt_gr: bool,
+    caps_lock: bool,
+    num_lock: bool,
+    scroll_lock: bool,
+    /// Lock key being held, which shouldn't toggle again when repeated
+    lock_held: Option<u8>,
+}
+
+impl Ps2Keyboard {
+    /// Create a decoder for scan code `set` with all modifiers and locks off
+    pub fn new(set: ScancodeSet, layout: Layout) -> Self {
+        Ps2Keyboard {
+            set,
+            layout,
+            extended: false,
+            release: false,
+            skip: 0,
+            left_shift: false,
+            right_shift: false,
+            left_control: false,
+            right_control: false,
+            alt: false,
+            alt_gr: false,
+            caps_lock: false,
+            num_lock: false,
+            scroll_lock: false,
+            lock_held: None,
+        }
+    }
+
+    /// Change the keyboard layout
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    /// Whether Caps Lock is on
+    pub fn caps_lock(&self) -> bool {
+        self.caps_lock
+    }
+
+    /// Whether Num Lock is on
+    pub fn num_lock(&self) -> bool {
+        self.num_lock
+    }
+
+    /// Whether Scroll Lock is on
+    pub fn scroll_lock(&self) -> bool {
+        self.scroll_lock
+    }
+
+    /// Decode a byte from the keyboard, returning the key pressed if it completes one
+    ///
+    /// Releases of keys and presses of modifier and lock keys return `None`.
+    pub fn add_byte(&mut self, byte: u8) -> Option<(Key, Modifiers)> {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return None;
+        }
+        if REPLIES.contains(&byte) || (self.set == ScancodeSet::Set2 && byte == 0xaa) {
+            return None;
+        }
+        match (self.set, byte) {
+            (_, 0xe0) => self.extended = true,
+            (ScancodeSet::Set1, 0xe1) => self.skip = 5,
+            (ScancodeSet::Set2, 0xe1) => self.skip = 7,
+            (ScancodeSet::Set2, 0xf0) => self.release = true,
+            (ScancodeSet::Set1, _) => {
+                let extended = core::mem::take(&mut self.extended);
+                return self.key(byte & 0x7f, extended, byte & 0x80 == 0);
+            }
+            (ScancodeSet::Set2, _) => {
+                let extended = core::mem::take(&mut self.extended);
+                let pressed = !core::mem::take(&mut self.release);
+                let code = SET2.iter().position(|&code| code == byte)?;
+                return self.key(code as u8, extended, pressed);
+            }
+        }
+        None
+    }
+
+    /// Handle the key of set 1 `code` being pressed or released
+    fn key(&mut self, code: u8, extended: bool, pressed: bool) -> Option<(Key, Modifiers)> {
+        match (extended, code) {
+            (false, 0x2a) => self.left_shift = pressed,
+            (false, 0x36) => self.right_shift = pressed,
+            (false, 0x1d) => self.left_control = pressed,
+            (true, 0x1d) => self.right_control = pressed,
+            (false, 0x38) => self.alt = pressed,
+            (true, 0x38) => self.alt_gr = pressed,
+            (false, 0x3a | 0x45 | 0x46) => {
+                if !pressed {
+                    self.lock_held = None;
+                } else if self.lock_held != Some(code) {
+                    self.lock_held = Some(code);
+                    let lock = match code {
+                        0x3a => &mut self.caps_lock,
+                        0x45 => &mut self.num_lock,
+                        _ => &mut self.scroll_lock,
+                    };
+                    *lock = !*lock;
+                }
+            }
+            _ if pressed => return self.press(code, extended),
+            _ => {}
+        }
+        None
+    }
+
+    /// Key and modifiers for a press of non-modifier key `code`
+    fn press(&self, code: u8, extended: bool) -> Option<(Key, Modifiers)> {
+        let shift = self.left_shift || self.right_shift;
+        let mut modifiers = Modifiers::empty();
+        if shift {
+            modifiers |= Modifiers::SHIFT;
+        }
+        if self.left_control || self.right_control {
+            modifiers |= Modifiers::CONTROL;
+        }
+        // AltGr is Alt on layouts without characters for it
+        let alt_gr = self.alt_gr && self.layout != Layout::Us;
+        if self.alt || (self.alt_gr && !alt_gr) {
+            modifiers |= Modifiers::ALT;
+        }
+        let key = match (extended, code) {
+            (false, 0x01) => Key::Escape,
+            (false, 0x0e) => Key::Backspace,
+            (false, 0x0f) => Key::Tab,
+            (false, 0x1c) => Key::Enter,
+            (false, 0x39) => Key::Char(' '),
+            (false, 0x3b..=0x44) => Key::F(code - 0x3a),
+            (false, 0x57 | 0x58) => Key::F(code - 0x4c),
+            (false, 0x37) => Key::Keypad('*'),
+            (false, 0x4a) => Key::Keypad('-'),
+            (false, 0x4e) => Key::Keypad('+'),
+            (false, 0x47..=0x53) if self.num_lock => {
+                Key::Keypad(b"789-456+1230."[code as usize - 0x47] as char)
+            }
+            (_, 0x47) => Key::Home,
+            (_, 0x48) => Key::Up,
+            (_, 0x49) => Key::PageUp,
+            (_, 0x4b) => Key::Left,
+            (_, 0x4d) => Key::Right,
+            (_, 0x4f) => Key::End,
+            (_, 0x50) => Key::Down,
+            (_, 0x51) => Key::PageDown,
+            (_, 0x52) => Key::Insert,
+            (_, 0x53) => Key::Delete,
+            (true, 0x1c) => Key::KeypadEnter,
+            (true, 0x35) => Key::Keypad('/'),
+            (false, _) => {
+                let index = char_index(code)?;
+                let [normal, shifted, with_alt_gr] = self.layout.keys();
+                let c = |keys: &str| keys.chars().nth(index).filter(|&c| c != ' ');
+                let normal = c(normal)?;
+                let shifted = c(shifted);
+                // Caps Lock shifts letters only
+                let letter = normal.is_lowercase() && shifted.is_some_and(char::is_uppercase);
+                let c = match c(with_alt_gr) {
+                    Some(c) if alt_gr => c,
+                    _ if shift != (self.caps_lock && letter) => shifted?,
+                    _ => normal,
+                };
+                modifiers.remove(Modifiers::SHIFT);
+                Key::Char(c)
+            }
+            _ => return None,
+        };
+        Some((key, modifiers))
+    }
+}
+
+impl Layout {
+    fn keys(self) -> [&'static str; 3] {
+        match self {
+            Layout::Us => US,
+            Layout::De => DE,
+            Layout::Fr => FR,
+        }
+    }
+}
+
+/// Index of the character key with set 1 `code` in the layout tables
+fn char_index(code: u8) -> Option<usize> {
+    let index = match code {
+        0x02..=0x0d => code - 0x02,
+        0x10..=0x1b => code - 0x10 + 12,
+        0x1e..=0x29 => code - 0x1e + 24,
+        0x2b..=0x35 => code - 0x2b + 36,
+        0x56 => 47,
+        _ => return None,
+    };
+    Some(index as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Keys pressed when `keyboard` decodes `bytes`
+    fn keys(keyboard: &mut Ps2Keyboard, bytes: &[u8]) -> Vec<(Key, Modifiers)> {
+        bytes.iter().filter_map(|&b| keyboard.add_byte(b)).collect()
+    }
+
+    /// Characters typed when `bytes` in set 1 are decoded with `layout`
+    fn chars(layout: Layout, bytes: &[u8]) -> Vec<char> {
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set1, layout);
+        keys(&mut keyboard, bytes)
+            .into_iter()
+            .map(|(key, modifiers)| match key {
+                Key::Char(c) if modifiers.is_empty() => c,
+                _ => panic!("unexpected key {:?} {:?}", key, modifiers),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn set2_table_matches_set1() {
+        // Esc, 1, q, a, z, Space, F7, F12, keypad 0 and the ISO key
+        let pairs = [
+            (0x01, 0x76),
+            (0x02, 0x16),
+            (0x10, 0x15),
+            (0x1e, 0x1c),
+            (0x2c, 0x1a),
+            (0x39, 0x29),
+            (0x41, 0x83),
+            (0x58, 0x07),
+            (0x52, 0x70),
+            (0x56, 0x61),
+        ];
+        for (set1, set2) in pairs {
+            assert_eq!(SET2[set1], set2);
+        }
+    }
+
+    #[test]
+    fn layout_tables() {
+        for layout in [Layout::Us, Layout::De, Layout::Fr] {
+            for keys in layout.keys().iter().filter(|keys| !keys.is_empty()) {
+                assert_eq!(keys.chars().count(), 48);
+            }
+        }
+    }
+
+    #[test]
+    fn german_layout() {
+        // z, y, Shift+z, ß, Shift+ß, #, ISO key
+        let bytes = [
+            0x15, 0x2c, 0x2a, 0x15, 0xaa, 0x0c, 0x36, 0x0c, 0xb6, 0x2b, 0x56,
+        ];
+        assert_eq!(
+            chars(Layout::De, &bytes),
+            ['z', 'y', 'Z', 'ß', '?', '#', '<']
+        );
+        // AltGr+q, AltGr+ß, AltGr+m, AltGr+ISO key
+        let bytes = [0xe0, 0x38, 0x10, 0x0c, 0x32, 0x56, 0xe0, 0xb8, 0x10];
+        assert_eq!(chars(Layout::De, &bytes), ['@', '\\', 'µ', '|', 'q']);
+        // Caps Lock shifts letters only
+        let bytes = [0x3a, 0xba, 0x27, 0x02, 0x2a, 0x27, 0xaa, 0x3a, 0xba, 0x27];
+        assert_eq!(chars(Layout::De, &bytes), ['Ö', '1', 'ö', 'ö']);
+    }
+
+    #[test]
+    fn french_layout() {
+        // &, Shift+&, a, Caps Lock a, Caps Lock &, ù, Shift+ù
+        let bytes = [
+            0x02, 0x2a, 0x02, 0xaa, 0x10, 0x3a, 0xba, 0x10, 0x02, 0x3a, 0xba,
+        ];
+        assert_eq!(chars(Layout::Fr, &bytes), ['&', '1', 'a', 'A', '&']);
+        let bytes = [0x28, 0x2a, 0x28, 0xaa];
+        assert_eq!(chars(Layout::Fr, &bytes), ['ù', '%']);
+        // AltGr+é, AltGr+à, AltGr+e, AltGr+$
+        let bytes = [0xe0, 0x38, 0x03, 0x0b, 0x12, 0x1b];
+        assert_eq!(chars(Layout::Fr, &bytes), ['~', '@', '€', '¤']);
+    }
+
+    #[test]
+    fn alt_gr_is_alt_on_us_layout() {
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set1, Layout::Us);
+        let keys = keys(&mut keyboard, &[0xe0, 0x38, 0x10]);
+        assert_eq!(keys, [(Key::Char('q'), Modifiers::ALT)]);
+    }
+
+    #[test]
+    fn extended_break_codes() {
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set1, Layout::Us);
+        let bytes = [0xe0, 0x1d, 0x1e, 0xe0, 0x9d, 0x1e, 0xe0, 0x48, 0xe0, 0xc8];
+        let expected = [
+            (Key::Char('a'), Modifiers::CONTROL),
+            (Key::Char('a'), Modifiers::empty()),
+            (Key::Up, Modifiers::empty()),
+        ];
+        assert_eq!(keys(&mut keyboard, &bytes), expected);
+
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set2, Layout::Us);
+        let bytes = [
+            0xe0, 0x14, 0x1c, 0xe0, 0xf0, 0x14, 0xf0, 0x1c, 0x1c, 0xe0, 0x75, 0xe0, 0xf0, 0x75,
+        ];
+        assert_eq!(keys(&mut keyboard, &bytes), expected);
+    }
+
+    #[test]
+    fn pause() {
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set1, Layout::Us);
+        let bytes = [0xe1, 0x1d, 0x45, 0xe1, 0x9d, 0xc5, 0x1e];
+        assert_eq!(
+            keys(&mut keyboard, &bytes),
+            [(Key::Char('a'), Modifiers::empty())]
+        );
+        assert!(!keyboard.num_lock());
+
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set2, Layout::Us);
+        let bytes = [0xe1, 0x14, 0x77, 0xe1, 0xf0, 0x14, 0xf0, 0x77, 0x1c];
+        assert_eq!(
+            keys(&mut keyboard, &bytes),
+            [(Key::Char('a'), Modifiers::empty())]
+        );
+        assert!(!keyboard.num_lock());
+    }
+
+    #[test]
+    fn lock_keys_toggle_once_while_held() {
+        let mut keyboard = Ps2Keyboard::new(ScancodeSet::Set2, Layout::Us);
+        keys(&mut keyboard, &[0x58, 0x58, 0x58]);
+        assert!(keyboard.caps_lock());
+        keys(&mut keyboard, &[0xf0, 0x58, 0x58, 0xf0, 0x58]);
+        assert!(!keyboard.caps_lock());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::font::MonoFontSet;
+use alloc::vec::Vec;
+use embedded_graphics::{
+    image::ImageRaw,
+    mono_font::{mapping::GlyphMapping, DecorationDimensions, MonoFont},
+    prelude::Size,
+};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE512: u8 = 0x01;
+const PSF1_MODEHASTAB: u8 = 0x02;
+const PSF1_MODESEQ: u8 = 0x04;
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+const PSF1_STARTSEQ: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_STARTSEQ: u8 = 0xFE;
+
+/// Error when parsing a PSF font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsfError {
+    /// The data doesn't start with a PSF1 or PSF2 magic number
+    InvalidMagic,
+    /// The header describes an empty or inconsistent font
+    InvalidHeader,
+    /// The data ends before all glyphs are read
+    Truncated,
+    /// The Unicode table is malformed
+    InvalidUnicodeTable,
+}
+
+/// A Linux console font in PSF1 or PSF2 format (`.psf` / `.psfu`)
+///
+/// The glyphs are borrowed from the font data without copying.
+/// Characters are mapped to glyphs with the Unicode table if the font has one,
+/// otherwise by their code points.
+/// Characters without a glyph are drawn with a fallback glyph, which is
+/// U+FFFD or `?` if the font has them, and the first glyph otherwise.
+///
+/// The font is used for all text styles on [`TextOnGraphic`](crate::TextOnGraphic).
+pub struct PsfFont<'a> {
+    glyphs: &'a [u8],
+    size: Size,
+    count: usize,
+    /// Characters with their glyph index, sorted by character
+    unicode: Vec<(char, usize)>,
+    fallback: usize,
+}
+
+impl<'a> PsfFont<'a> {
+    /// Parse a PSF1 or PSF2 font from `data`
+    pub fn parse(data: &'a [u8]) -> Result<Self, PsfError> {
+        if data.starts_with(&PSF1_MAGIC) {
+            Self::parse_psf1(data)
+        } else if data.starts_with(&PSF2_MAGIC) {
+            Self::parse_psf2(data)
+        } else {
+            Err(PsfError::InvalidMagic)
+        }
+    }
+
+    fn parse_psf1(data: &'a [u8]) -> Result<Self, PsfError> {
+        let mode = *data.get(2).ok_or(PsfError::Truncated)?;
+        let height = *data.get(3).ok_or(PsfError::Truncated)? as usize;
+        let count = if mode & PSF1_MODE512 != 0 { 512 } else { 256 };
+        let (glyphs, table) = split_glyphs(&data[4..], count, height)?;
+        let mut unicode = Vec::new();
+        if mode & (PSF1_MODEHASTAB | PSF1_MODESEQ) != 0 {
+            let mut values = table
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]));
+            for index in 0..count {
+                let mut in_sequence = false;
+                loop {
+                    match values.next().ok_or(PsfError::InvalidUnicodeTable)? {
+                        PSF1_SEPARATOR => break,
+                        PSF1_STARTSEQ => in_sequence = true,
+                        // sequences of combining characters can't be drawn as one cell
+                        _ if in_sequence => {}
+                        value => unicode.extend(char::from_u32(value as u32).map(|c| (c, index))),
+                    }
+                }
+            }
+        }
+        Ok(Self::new(
+            glyphs,
+            Size::new(8, height as u32),
+            count,
+            unicode,
+        ))
+    }
+
+    fn parse_psf2(data: &'a [u8]) -> Result<Self, PsfError> {
+        let header = |i: usize| -> Result<u32, PsfError> {
+            let bytes = data.get(i * 4..i * 4 + 4).ok_or(PsfError::Truncated)?;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let header_size = header(2)? as usize;
+        let flags = header(3)?;
+        let count = header(4)? as usize;
+        let char_size = header(5)? as usize;
+        let height = header(6)? as usize;
+        let width = header(7)? as usize;
+        if width == 0 || height.checked_mul(width.div_ceil(8)) != Some(char_size) {
+            return Err(PsfError::InvalidHeader);
+        }
+        let data = data.get(header_size..).ok_or(PsfError::Truncated)?;
+        let (glyphs, table) = split_glyphs(data, count, char_size)?;
+        let mut unicode = Vec::new();
+        if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+            let mut entries = table.split(|&b| b == PSF2_SEPARATOR);
+            for index in 0..count {
+                let entry = entries.next().ok_or(PsfError::InvalidUnicodeTable)?;
+                // sequences of combining characters can't be drawn as one cell
+                let chars = entry.split(|&b| b == PSF2_STARTSEQ).next().unwrap();
+                let chars =
+                    core::str::from_utf8(chars).map_err(|_| PsfError::InvalidUnicodeTable)?;
+                unicode.extend(chars.chars().map(|c| (c, index)));
+            }
+        }
+        Ok(Self::new(
+            glyphs,
+            Size::new(width as u32, height as u32),
+            count,
+            unicode,
+        ))
+    }
+
+    fn new(glyphs: &'a [u8], size: Size, count: usize, mut unicode: Vec<(char, usize)>) -> Self {
+        // keep the first glyph of characters mapped more than once
+        unicode.sort_by_key(|&(c, _)| c);
+        unicode.dedup_by_key(|&mut (c, _)| c);
+        let mut font = PsfFont {
+            glyphs,
+            size,
+            count,
+            unicode,
+            fallback: 0,
+        };
+        font.fallback = font
+            .lookup('\u{FFFD}')
+            .or_else(|| font.lookup('?'))
+            .unwrap_or(0);
+        font
+    }
+
+    /// Draw characters without a glyph with the glyph of `c`
+    ///
+    /// The fallback glyph is unchanged if the font has no glyph for `c` either.
+    pub fn with_fallback(mut self, c: char) -> Self {
+        if let Some(index) = self.lookup(c) {
+            self.fallback = index;
+        }
+        self
+    }
+
+    /// Number of glyphs in the font
+    pub fn glyph_count(&self) -> usize {
+        self.count
+    }
+
+    /// Size of a glyph in pixels
+    pub fn glyph_size(&self) -> Size {
+        self.size
+    }
+
+    /// Index of the glyph for `c`, if the font has one
+    fn lookup(&self, c: char) -> Option<usize> {
+        if self.unicode.is_empty() {
+            Some(c as usize).filter(|&index| index < self.count)
+        } else {
+            self.unicode
+                .binary_search_by_key(&c, |&(c, _)| c)
+                .ok()
+                .map(|i| self.unicode[i].1)
+        }
+    }
+}
+
+/// Split `data` into `count` glyphs of `char_size` bytes and the rest
+fn split_glyphs(data: &[u8], count: usize, char_size: usize) -> Result<(&[u8], &[u8]), PsfError> {
+    if count == 0 || char_size == 0 {
+        return Err(PsfError::InvalidHeader);
+    }
+    let len = count
+        .checked_mul(char_size)
+        .ok_or(PsfError::InvalidHeader)?;
+    if data.len() < len {
+        return Err(PsfError::Truncated);
+    }
+    Ok(data.split_at(len))
+}
+
+impl GlyphMapping for PsfFont<'_> {
+    fn index(&self, c: char) -> usize {
+        self.lookup(c).unwrap_or(self.fallback)
+    }
+}
+
+impl MonoFontSet for PsfFont<'_> {
+    fn regular(&self) -> MonoFont<'_> {
+        let height = self.size.height;
+        let baseline = height * 3 / 4;
+        MonoFont {
+            // one glyph per row, each row padded to whole bytes as in PSF
+            image: ImageRaw::new(self.glyphs, self.size.width),
+            character_size: self.size,
+            character_spacing: 0,
+            baseline,
+            strikethrough: DecorationDimensions::default_strikethrough(height),
+            underline: DecorationDimensions::new((baseline + 1).min(height - 1), 1),
+            glyph_mapping: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::prelude::OriginDimensions;
+
+    /// PSF1 font of 256 glyphs 8x2, with a Unicode table if `table` isn't empty
+    fn psf1(table: &[u16]) -> Vec<u8> {
+        let mode = if table.is_empty() { 0 } else { PSF1_MODEHASTAB };
+        let mut data = vec![PSF1_MAGIC[0], PSF1_MAGIC[1], mode, 2];
+        data.extend((0..256 * 2).map(|i| i as u8));
+        data.extend(table.iter().flat_map(|value| value.to_le_bytes()));
+        data
+    }
+
+    /// PSF2 font of 2 glyphs 10x2 with Unicode table `table`
+    fn psf2(table: &[u8]) -> Vec<u8> {
+        let mut data = PSF2_MAGIC.to_vec();
+        for value in [0, 32, PSF2_HAS_UNICODE_TABLE, 2, 4, 2, 10] {
+            data.extend(u32::to_le_bytes(value));
+        }
+        data.extend([0xff, 0xc0, 0x80, 0x40, 0x00, 0x00, 0xff, 0xc0]);
+        data.extend(table);
+        data
+    }
+
+    #[test]
+    fn psf1_with_unicode_table() {
+        let mut table = vec![
+            'A' as u16,
+            PSF1_SEPARATOR,
+            'B' as u16,
+            0x0391,
+            PSF1_SEPARATOR,
+            PSF1_STARTSEQ,
+            'e' as u16,
+            0x0301,
+            PSF1_SEPARATOR,
+            '?' as u16,
+            'A' as u16,
+            PSF1_SEPARATOR,
+        ];
+        table.extend([PSF1_SEPARATOR; 252]);
+        let data = psf1(&table);
+        let font = PsfFont::parse(&data).unwrap();
+        assert_eq!(font.glyph_count(), 256);
+        assert_eq!(font.glyph_size(), Size::new(8, 2));
+        assert_eq!(font.index('A'), 0);
+        assert_eq!(font.index('B'), 1);
+        assert_eq!(font.index('\u{391}'), 1);
+        assert_eq!(font.index('e'), 3);
+        assert_eq!(font.with_fallback('B').index('x'), 1);
+    }
+
+    #[test]
+    fn psf1_without_unicode_table() {
+        let data = psf1(&[]);
+        let font = PsfFont::parse(&data).unwrap();
+        assert_eq!(font.index('A'), 0x41);
+        assert_eq!(font.index('\u{100}'), '?' as usize);
+        let regular = font.regular();
+        assert_eq!(regular.character_size, Size::new(8, 2));
+        assert_eq!(regular.image.size(), Size::new(8, 512));
+    }
+
+    #[test]
+    fn psf1_truncated_unicode_table() {
+        let data = psf1(&['A' as u16, PSF1_SEPARATOR]);
+        assert_eq!(
+            PsfFont::parse(&data).err(),
+            Some(PsfError::InvalidUnicodeTable)
+        );
+    }
+
+    #[test]
+    fn psf2_with_unicode_table() {
+        let mut table = "a\u{e9}".as_bytes().to_vec();
+        table.push(PSF2_SEPARATOR);
+        table.extend("\u{fffd}".as_bytes());
+        table.push(PSF2_STARTSEQ);
+        table.extend("e\u{301}".as_bytes());
+        table.push(PSF2_SEPARATOR);
+        let data = psf2(&table);
+        let font = PsfFont::parse(&data).unwrap();
+        assert_eq!(font.glyph_count(), 2);
+        assert_eq!(font.glyph_size(), Size::new(10, 2));
+        assert_eq!(font.index('a'), 0);
+        assert_eq!(font.index('\u{e9}'), 0);
+        assert_eq!(font.index('z'), 1);
+        assert_eq!(font.regular().image.size(), Size::new(10, 4));
+    }
+
+    #[test]
+    fn invalid_fonts() {
+        assert_eq!(PsfFont::parse(b"font").err(), Some(PsfError::InvalidMagic));
+        let data = psf1(&[]);
+        assert_eq!(
+            PsfFont::parse(&data[..100]).err(),
+            Some(PsfError::Truncated)
+        );
+        let mut data = psf2(&[PSF2_SEPARATOR; 2]);
+        data[20] = 3;
+        assert_eq!(PsfFont::parse(&data).err(), Some(PsfError::InvalidHeader));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::cell::Cell;
+use crate::ansi::CursorShape;
+use crate::cell::{Cell, Flags};
+use core::cmp::min;
 
 /// A 2D array of `Cell` to render on screen
 pub trait TextBuffer {
@@ -16,6 +18,30 @@ pub trait TextBuffer {
     /// Write a character `ch` at `(row, col)`
     fn write(&mut self, row: usize, col: usize, cell: Cell);
 
+    /// Write `cells` to consecutive columns of `row`, starting from `col`.
+    ///
+    /// The default method writes each cell separately.
+    fn write_run(&mut self, row: usize, col: usize, cells: &[Cell]) {
+        for (i, &cell) in cells.iter().enumerate() {
+            self.write(row, col + i, cell);
+        }
+    }
+
+    /// Write `cell` at `(row, col)` with the cursor of `shape` on top of it.
+    ///
+    /// The default method shows the cursor by inverting the colors of the cell.
+    fn write_with_cursor(&mut self, row: usize, col: usize, cell: Cell, _shape: CursorShape) {
+        let mut cell = cell;
+        cell.flags.toggle(Flags::INVERSE);
+        self.write(row, col, cell);
+    }
+
+    /// Show the cursor at `(row, col)` with `shape`, or hide it if `pos` is `None`.
+    ///
+    /// The default method does nothing.
+    /// Buffers with a hardware cursor can rewrite it to move the cursor.
+    fn set_cursor(&mut self, _pos: Option<(usize, usize)>, _shape: CursorShape) {}
+
     /// Delete one character at `(row, col)`.
     fn delete(&mut self, row: usize, col: usize) {
         self.write(row, col, Cell::default());
@@ -36,6 +62,52 @@ pub trait TextBuffer {
         }
     }
 
+    /// Scroll rows in `top..bottom` up by `n` lines, or down if `n` is negative.
+    ///
+    /// Rows scrolled out of the region are discarded,
+    /// and rows exposed on the other side are filled with `fill`.
+    ///
+    /// The default method does single read and write for each pixel.
+    /// Usually it needs rewrite for better performance.
+    fn scroll_region(&mut self, top: usize, bottom: usize, n: isize, fill: Cell) {
+        let bottom = min(bottom, self.height());
+        if top >= bottom || n == 0 {
+            return;
+        }
+        let lines = min(n.unsigned_abs(), bottom - top);
+        if n > 0 {
+            for i in top..bottom - lines {
+                for j in 0..self.width() {
+                    self.write(i, j, self.read(i + lines, j));
+                }
+            }
+            for i in bottom - lines..bottom {
+                for j in 0..self.width() {
+                    self.write(i, j, fill);
+                }
+            }
+        } else {
+            for i in (top + lines..bottom).rev() {
+                for j in 0..self.width() {
+                    self.write(i, j, self.read(i - lines, j));
+                }
+            }
+            for i in top..top + lines {
+                for j in 0..self.width() {
+                    self.write(i, j, fill);
+                }
+            }
+        }
+    }
+
+    /// Whether [`scroll_region`](Self::scroll_region) is faster than rewriting the scrolled cells.
+    ///
+    /// [`TextBufferCache`](crate::TextBufferCache) only scrolls the inner buffer if so,
+    /// and rewrites the changed cells otherwise. The default method returns `false`.
+    fn can_scroll(&self) -> bool {
+        false
+    }
+
     /// Clear the buffer
     fn clear(&mut self, cell: Cell) {
         for i in 0..self.height() {
@@ -44,4 +116,23 @@ pub trait TextBuffer {
             }
         }
     }
+
+    /// Switch between the primary and the alternate screen.
+    ///
+    /// If `fill` is given, the screen switched to is filled with it instead of its old content.
+    /// The default method doesn't keep the screen switched from, and only fills the buffer.
+    /// [`TextBufferCache`](crate::TextBufferCache) keeps both screens.
+    fn swap_screen(&mut self, fill: Option<Cell>) {
+        if let Some(cell) = fill {
+            self.clear(cell);
+        }
+    }
+
+    /// Clear lines scrolled off the top of the screen, if the buffer keeps any.
+    fn clear_history(&mut self) {}
+
+    /// Enable or disable saving lines scrolled off the top of the screen.
+    ///
+    /// It is disabled while the alternate screen is shown.
+    fn set_history_enabled(&mut self, _enabled: bool) {}
 }
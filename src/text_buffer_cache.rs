@@ -1,34 +1,266 @@
+use crate::ansi::CursorShape;
 use crate::cell::Cell;
 use crate::text_buffer::TextBuffer;
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
+use core::cmp::min;
+use core::ops::Range;
+
+/// Default maximum number of lines in history of [`TextBufferCache`]
+///
+/// Lines are only allocated when they are scrolled off the screen.
+pub const DEFAULT_HISTORY_SIZE: usize = 200;
 
 /// Cache layer for [`TextBuffer`]
+///
+/// It also keeps lines scrolled off the top of the screen as history,
+/// which can be viewed with [`scroll_view`](Self::scroll_view).
+///
+/// In [deferred mode](Self::set_deferred), changes are only written to the inner buffer
+/// on [`flush`](Self::flush), skipping cells that end up unchanged.
 pub struct TextBufferCache<T: TextBuffer> {
     buf: Vec<Vec<Cell>>,
     row_offset: usize,
+    /// Lines scrolled off the top, oldest first
+    history: VecDeque<Vec<Cell>>,
+    /// Maximum number of lines in history
+    history_size: usize,
+    /// Whether scrolled lines are saved to history
+    history_enabled: bool,
+    /// Screen not currently shown, allocated on the first switch to the alternate screen
+    inactive: Vec<Vec<Cell>>,
+    /// Number of history lines shown above the screen
+    view_offset: usize,
+    /// Position and shape of the cursor drawn on screen
+    cursor: Option<(usize, usize, CursorShape)>,
+    /// Whether writes to the inner buffer are deferred until flush
+    deferred: bool,
+    /// Cells and cursor shown on each row of the screen, kept in deferred mode
+    shown: Vec<Vec<(Cell, Option<CursorShape>)>>,
+    /// Columns of each row of the screen changed since last flush
+    dirty: Vec<Range<usize>>,
     inner: T,
 }
 
 impl<T: TextBuffer> TextBufferCache<T> {
     /// Create a cache layer for `inner` text buffer
+    ///
+    /// Up to [`DEFAULT_HISTORY_SIZE`] lines of history are kept,
+    /// which can be changed with [`set_history_size`](Self::set_history_size).
     pub fn new(inner: T) -> Self {
         TextBufferCache {
             buf: vec![vec![Cell::default(); inner.width()]; inner.height()],
             row_offset: 0,
+            history: VecDeque::new(),
+            history_size: DEFAULT_HISTORY_SIZE,
+            history_enabled: true,
+            inactive: Vec::new(),
+            view_offset: 0,
+            cursor: None,
+            deferred: false,
+            shown: Vec::new(),
+            dirty: Vec::new(),
             inner,
         }
     }
-    /// Get real row of inner buffer
-    fn real_row(&self, row: usize) -> usize {
-        (self.row_offset + row) % self.inner.height()
+
+    /// The inner text buffer
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Enable or disable deferred mode
+    ///
+    /// In deferred mode, writes only change the cache until [`flush`](Self::flush) is called.
+    /// Disabling it flushes pending changes.
+    pub fn set_deferred(&mut self, deferred: bool) {
+        if deferred == self.deferred {
+            return;
+        }
+        if deferred {
+            self.shown = (0..self.height())
+                .map(|row| {
+                    (0..self.width())
+                        .map(|col| self.shown_cell(row, col))
+                        .collect()
+                })
+                .collect();
+            self.dirty = vec![0..0; self.height()];
+            self.deferred = true;
+        } else {
+            self.flush();
+            self.deferred = false;
+            self.shown = Vec::new();
+            self.dirty = Vec::new();
+        }
+    }
+
+    /// Write cells changed since last flush to the inner buffer
+    ///
+    /// Consecutive changed cells in a row are written in one [`TextBuffer::write_run`].
+    /// It does nothing unless in deferred mode.
+    pub fn flush(&mut self) {
+        if !self.deferred {
+            return;
+        }
+        let mut run = Vec::with_capacity(self.width());
+        for row in 0..self.height() {
+            let cols = core::mem::replace(&mut self.dirty[row], 0..0);
+            let mut run_start = cols.start;
+            for col in cols {
+                let (cell, cursor) = self.shown_cell(row, col);
+                if self.shown[row][col] == (cell, cursor) {
+                    self.flush_run(row, run_start, &mut run);
+                    continue;
+                }
+                self.shown[row][col] = (cell, cursor);
+                if let Some(shape) = cursor {
+                    self.flush_run(row, run_start, &mut run);
+                    self.inner.write_with_cursor(row, col, cell, shape);
+                    continue;
+                }
+                if run.is_empty() {
+                    run_start = col;
+                }
+                run.push(cell);
+            }
+            self.flush_run(row, run_start, &mut run);
+        }
+    }
+
+    /// Set the maximum number of lines kept in history, or disable it with `0`
+    pub fn set_history_size(&mut self, lines: usize) {
+        self.scroll_to_bottom();
+        self.history_size = lines;
+        while self.history.len() > lines {
+            self.history.pop_front();
+        }
+    }
+
+    /// Number of lines in history
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Scroll the view `lines` up into history, or down if `lines` is negative
+    pub fn scroll_view(&mut self, lines: isize) {
+        let offset = if lines > 0 {
+            min(self.view_offset + lines as usize, self.history.len())
+        } else {
+            self.view_offset.saturating_sub(lines.unsigned_abs())
+        };
+        self.set_view_offset(offset);
+    }
+
+    /// Scroll the view back to the bottom of the screen
+    pub fn scroll_to_bottom(&mut self) {
+        self.set_view_offset(0);
     }
-    /// Clear line at `row`
-    fn clear_line(&mut self, row: usize, cell: Cell) {
-        for col in 0..self.width() {
-            self.buf[row][col] = cell;
+
+    /// Line shown at `row` when `offset` lines of history are in view
+    fn view_line(&self, row: usize, offset: usize) -> &[Cell] {
+        if row < offset {
+            &self.history[self.history.len() - offset + row]
+        } else {
+            &self.buf[self.real_row(row - offset)]
+        }
+    }
+    /// Show `offset` lines of history, only redrawing cells that change
+    fn set_view_offset(&mut self, offset: usize) {
+        if offset == self.view_offset {
+            return;
+        }
+        let cursor = self.hide_cursor();
+        for row in 0..self.height() {
+            for col in 0..self.width() {
+                let old = self.view_line(row, self.view_offset)[col];
+                let new = self.view_line(row, offset)[col];
+                if old != new {
+                    self.draw(row, col, new);
+                }
+            }
+        }
+        self.view_offset = offset;
+        self.show_cursor(cursor);
+    }
+    /// Cell and cursor to show at `(row, col)` of the screen
+    fn shown_cell(&self, row: usize, col: usize) -> (Cell, Option<CursorShape>) {
+        let cell = self.view_line(row, self.view_offset)[col];
+        match self.cursor {
+            Some((r, c, shape)) if (r, c) == (row, col) && self.view_offset == 0 => {
+                (cell, Some(shape))
+            }
+            _ => (cell, None),
+        }
+    }
+    /// Write `run` starting at `(row, col)` of the screen, and empty it
+    fn flush_run(&mut self, row: usize, col: usize, run: &mut Vec<Cell>) {
+        if !run.is_empty() {
+            self.inner.write_run(row, col, run);
+            run.clear();
+        }
+    }
+    /// Write `cell` at `(row, col)` of the screen, or mark it dirty in deferred mode
+    fn draw(&mut self, row: usize, col: usize, cell: Cell) {
+        if self.deferred {
+            let dirty = &mut self.dirty[row];
+            *dirty = if dirty.start == dirty.end {
+                col..col + 1
+            } else {
+                dirty.start.min(col)..dirty.end.max(col + 1)
+            };
+        } else {
             self.inner.write(row, col, cell);
         }
     }
+    /// Redraw cell at `(row, col)` of the screen, with the cursor if it is there
+    fn redraw_cell(&mut self, row: usize, col: usize) {
+        let cell = self.read(row, col);
+        match self.cursor {
+            Some((r, c, shape)) if (r, c) == (row, col) && !self.deferred => {
+                self.inner.write_with_cursor(row, col, cell, shape)
+            }
+            _ => self.draw(row, col, cell),
+        }
+    }
+    /// Remove the cursor from screen, returning it for [`show_cursor`](Self::show_cursor)
+    fn hide_cursor(&mut self) -> Option<(usize, usize, CursorShape)> {
+        let cursor = self.cursor.take();
+        if let Some((row, col, _)) = cursor {
+            if self.view_offset == 0 {
+                self.redraw_cell(row, col);
+            }
+        }
+        cursor
+    }
+    /// Draw the cursor on screen, unless history is in view
+    fn show_cursor(&mut self, cursor: Option<(usize, usize, CursorShape)>) {
+        self.cursor = cursor;
+        if let Some((row, col, _)) = cursor {
+            if self.view_offset == 0 {
+                self.redraw_cell(row, col);
+            }
+        }
+    }
+    /// Save line at `row` to history
+    fn push_history(&mut self, row: usize) {
+        if !self.history_enabled || self.history_size == 0 {
+            return;
+        }
+        let row = self.real_row(row);
+        let line = if self.history.len() >= self.history_size {
+            let mut line = self.history.pop_front().unwrap();
+            line.copy_from_slice(&self.buf[row]);
+            line
+        } else {
+            self.buf[row].clone()
+        };
+        self.history.push_back(line);
+    }
+    /// Get real row of inner buffer
+    fn real_row(&self, row: usize) -> usize {
+        (self.row_offset + row) % self.inner.height()
+    }
 }
 
 impl<T: TextBuffer> TextBuffer for TextBufferCache<T> {
@@ -50,20 +282,152 @@ impl<T: TextBuffer> TextBuffer for TextBufferCache<T> {
 
     #[inline]
     fn write(&mut self, row: usize, col: usize, cell: Cell) {
-        let row = self.real_row(row);
-        self.buf[row][col] = cell;
-        self.inner.write(row, col, cell);
+        self.scroll_to_bottom();
+        let real = self.real_row(row);
+        self.buf[real][col] = cell;
+        self.redraw_cell(row, col);
+    }
+
+    fn set_cursor(&mut self, pos: Option<(usize, usize)>, shape: CursorShape) {
+        let cursor = pos.map(|(row, col)| (row, col, shape));
+        if cursor == self.cursor {
+            return;
+        }
+        self.hide_cursor();
+        self.show_cursor(cursor);
+        self.inner.set_cursor(pos, shape);
     }
 
     #[inline]
     fn new_line(&mut self, cell: Cell) {
-        self.clear_line(self.row_offset, cell);
-        self.row_offset = (self.row_offset + 1) % self.inner.height();
+        self.scroll_region(0, self.height(), 1, cell);
+    }
+
+    fn scroll_region(&mut self, top: usize, bottom: usize, n: isize, fill: Cell) {
+        let bottom = min(bottom, self.height());
+        if top >= bottom || n == 0 {
+            return;
+        }
+        let lines = min(n.unsigned_abs(), bottom - top);
+        let height = self.height();
+        self.scroll_to_bottom();
+        let cursor = self.hide_cursor();
+        if n > 0 && top == 0 {
+            for row in 0..lines {
+                self.push_history(row);
+            }
+        }
+        // the line moved to `row`, or `None` if it is exposed
+        let source = |row: usize| {
+            if n > 0 {
+                Some(row + lines).filter(|&src| src < bottom)
+            } else {
+                row.checked_sub(lines).filter(|&src| src >= top)
+            }
+        };
+        if self.inner.can_scroll() {
+            self.inner.scroll_region(top, bottom, n, fill);
+            if self.deferred {
+                // what is shown moves along with the lines
+                let (shown, dirty) = (&mut self.shown[top..bottom], &mut self.dirty[top..bottom]);
+                let exposed = if n > 0 {
+                    shown.rotate_left(lines);
+                    dirty.rotate_left(lines);
+                    bottom - top - lines..bottom - top
+                } else {
+                    shown.rotate_right(lines);
+                    dirty.rotate_right(lines);
+                    0..lines
+                };
+                for row in exposed {
+                    shown[row].fill((fill, None));
+                    dirty[row] = 0..0;
+                }
+            }
+        } else {
+            // only redraw cells that change
+            for row in top..bottom {
+                let src = source(row);
+                for col in 0..self.width() {
+                    let cell = src.map_or(fill, |src| self.read(src, col));
+                    if self.read(row, col) != cell {
+                        self.draw(row, col, cell);
+                    }
+                }
+            }
+        }
+        if top == 0 && bottom == height {
+            // scrolling the whole cache only needs to rotate rows
+            self.row_offset = if n > 0 {
+                (self.row_offset + lines) % height
+            } else {
+                (self.row_offset + height - lines) % height
+            };
+        } else {
+            for i in 0..bottom - top {
+                // move lines in the order they are scrolled
+                let row = if n > 0 { top + i } else { bottom - 1 - i };
+                if let Some(src) = source(row) {
+                    let (dst, src) = (self.real_row(row), self.real_row(src));
+                    self.buf.swap(dst, src);
+                }
+            }
+        }
+        for row in (top..bottom).filter(|&row| source(row).is_none()) {
+            let real = self.real_row(row);
+            self.buf[real].fill(fill);
+        }
+        self.show_cursor(cursor);
     }
 
     #[inline]
     fn clear(&mut self, cell: Cell) {
+        let cursor = self.hide_cursor();
+        for line in self.buf.iter_mut() {
+            line.fill(cell);
+        }
         self.row_offset = 0;
-        self.inner.clear(cell);
+        self.view_offset = 0;
+        if self.deferred {
+            let width = self.width();
+            self.dirty.fill(0..width);
+        } else {
+            self.inner.clear(cell);
+        }
+        self.show_cursor(cursor);
+    }
+
+    fn swap_screen(&mut self, fill: Option<Cell>) {
+        self.scroll_to_bottom();
+        if self.inactive.is_empty() {
+            self.inactive = vec![vec![Cell::default(); self.width()]; self.height()];
+        }
+        if let Some(fill) = fill {
+            for line in self.inactive.iter_mut() {
+                line.fill(fill);
+            }
+        }
+        let cursor = self.hide_cursor();
+        for row in 0..self.height() {
+            let real = self.real_row(row);
+            core::mem::swap(&mut self.buf[real], &mut self.inactive[row]);
+            // only redraw cells that differ between the screens
+            for col in 0..self.width() {
+                let cell = self.buf[real][col];
+                if cell != self.inactive[row][col] {
+                    self.draw(row, col, cell);
+                }
+            }
+        }
+        self.show_cursor(cursor);
+    }
+
+    fn clear_history(&mut self) {
+        self.scroll_to_bottom();
+        self.history.clear();
+    }
+
+    fn set_history_enabled(&mut self, enabled: bool) {
+        self.history_enabled = enabled;
     }
 }
@@ -0,0 +1,501 @@
+use crate::ansi::CursorShape;
+use crate::cell::{Cell, Flags};
+use crate::color::Color;
+use crate::text_buffer::TextBuffer;
+use embedded_graphics::prelude::RgbColor;
+
+/// CRT controller index port for color text modes
+const CRTC_INDEX: u16 = 0x3d4;
+const CRTC_DATA: u16 = 0x3d5;
+const CRTC_MAX_SCAN_LINE: u8 = 0x09;
+const CRTC_CURSOR_START: u8 = 0x0a;
+const CRTC_CURSOR_END: u8 = 0x0b;
+const CRTC_CURSOR_HIGH: u8 = 0x0e;
+const CRTC_CURSOR_LOW: u8 = 0x0f;
+const CURSOR_DISABLE: u8 = 0x20;
+
+/// Reading this port resets the attribute controller to expect an index
+const INPUT_STATUS: u16 = 0x3da;
+const ATTR_INDEX: u16 = 0x3c0;
+const ATTR_DATA_READ: u16 = 0x3c1;
+/// Mode control register, with the bit to keep the palette enabled
+const ATTR_MODE_CONTROL: u8 = 0x10 | 0x20;
+const MODE_BLINK: u8 = 0x08;
+
+/// Top bit of an attribute byte, for blinking or a bright background
+const BLINK_BIT: u8 = 0x80;
+
+/// Code page 437 characters 0x00 to 0x1f
+const CP437_LOW: &str = " ☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+/// Code page 437 characters 0x80 to 0xff
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+    ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+    αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/// The 16 VGA colors in their default palette
+const PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (0, 0, 170),
+    (0, 170, 0),
+    (0, 170, 170),
+    (170, 0, 0),
+    (170, 0, 170),
+    (170, 85, 0),
+    (170, 170, 170),
+    (85, 85, 85),
+    (85, 85, 255),
+    (85, 255, 85),
+    (85, 255, 255),
+    (255, 85, 85),
+    (255, 85, 255),
+    (255, 255, 85),
+    (255, 255, 255),
+];
+
+/// Volatile access to the cells of VGA text memory, usually mapped at `0xb8000`
+///
+/// Each cell has a code page 437 character in the low byte and its attribute in the high byte.
+/// Implementations should use volatile reads and writes, so that they aren't optimized away.
+pub trait VgaMemory {
+    /// Read the cell at `index`
+    fn read(&self, index: usize) -> u16;
+
+    /// Write `value` to the cell at `index`
+    fn write(&mut self, index: usize, value: u16);
+}
+
+/// Port I/O to program the VGA registers, e.g. with the `in` and `out` instructions on x86
+pub trait VgaPorts {
+    /// Read a byte from `port`
+    fn read_port(&mut self, port: u16) -> u8;
+
+    /// Write a byte to `port`
+    fn write_port(&mut self, port: u16, value: u8);
+}
+
+/// Ports of a [`VgaTextBuffer`] without port I/O
+pub enum NoPorts {}
+
+impl VgaPorts for NoPorts {
+    fn read_port(&mut self, _port: u16) -> u8 {
+        match *self {}
+    }
+
+    fn write_port(&mut self, _port: u16, _value: u8) {
+        match *self {}
+    }
+}
+
+/// A [`TextBuffer`] on VGA text mode memory
+///
+/// Colors are mapped to the nearest of the 16 VGA colors, and bold text is drawn
+/// in bright colors. Characters are written in code page 437, with `?` for those not in it.
+///
+/// With [port I/O](Self::with_ports), the cursor is shown with the hardware cursor,
+/// where bar cursors are shown as underlines. Otherwise it is shown by inverting colors.
+pub struct VgaTextBuffer<M, P = NoPorts> {
+    memory: M,
+    ports: Option<P>,
+    width: usize,
+    height: usize,
+    blink: bool,
+    /// The hardware cursor last set
+    cursor: Option<(Option<(usize, usize)>, CursorShape)>,
+}
+
+impl<M: VgaMemory> VgaTextBuffer<M> {
+    /// Create a buffer of `width` columns and `height` rows on `memory`, usually 80x25
+    pub fn new(memory: M, width: usize, height: usize) -> Self {
+        VgaTextBuffer {
+            memory,
+            ports: None,
+            width,
+            height,
+            blink: true,
+            cursor: None,
+        }
+    }
+
+    /// Use `ports` to move the hardware cursor and set the blink mode
+    pub fn with_ports<Q: VgaPorts>(self, ports: Q) -> VgaTextBuffer<M, Q> {
+        VgaTextBuffer {
+            memory: self.memory,
+            ports: Some(ports),
+            width: self.width,
+            height: self.height,
+            blink: self.blink,
+            cursor: None,
+        }
+    }
+}
+
+impl<M: VgaMemory, P: VgaPorts> VgaTextBuffer<M, P> {
+    /// Use the top bit of attributes to blink text, or to brighten backgrounds if `false`.
+    ///
+    /// Blinking is enabled by default, as VGA is after boot. The mode of the hardware
+    /// is changed if there is port I/O, and should match `enabled` otherwise.
+    pub fn set_blink(&mut self, enabled: bool) {
+        self.blink = enabled;
+        if let Some(ports) = self.ports.as_mut() {
+            ports.read_port(INPUT_STATUS);
+            ports.write_port(ATTR_INDEX, ATTR_MODE_CONTROL);
+            let mode = ports.read_port(ATTR_DATA_READ);
+            let mode = if enabled {
+                mode | MODE_BLINK
+            } else {
+                mode & !MODE_BLINK
+            };
+            ports.write_port(ATTR_INDEX, mode);
+        }
+    }
+
+    /// Attribute byte of `cell`
+    fn attribute(&self, cell: &Cell) -> u8 {
+        let mut fg = vga_color(cell.fg);
+        let mut bg = vga_color(cell.bg);
+        if cell.flags.contains(Flags::BOLD) {
+            fg |= 0x08;
+        }
+        if cell.flags.contains(Flags::INVERSE) {
+            core::mem::swap(&mut fg, &mut bg);
+        }
+        let mut blink = 0;
+        if self.blink {
+            bg &= 0x07;
+            if cell.flags.contains(Flags::BLINK) {
+                blink = BLINK_BIT;
+            }
+        }
+        if cell.flags.contains(Flags::HIDDEN) {
+            fg = bg;
+        }
+        blink | bg << 4 | fg
+    }
+}
+
+impl<M: VgaMemory, P: VgaPorts> TextBuffer for VgaTextBuffer<M, P> {
+    #[inline]
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn read(&self, row: usize, col: usize) -> Cell {
+        let value = self.memory.read(row * self.width + col);
+        let attribute = (value >> 8) as u8;
+        let mut cell = Cell::default();
+        cell.c = decode(value as u8);
+        cell.fg = Color::Indexed(swap_red_blue(attribute & 0x0f));
+        if self.blink {
+            cell.bg = Color::Indexed(swap_red_blue((attribute >> 4) & 0x07));
+            cell.flags.set(Flags::BLINK, attribute & BLINK_BIT != 0);
+        } else {
+            cell.bg = Color::Indexed(swap_red_blue(attribute >> 4));
+        }
+        cell
+    }
+
+    fn write(&mut self, row: usize, col: usize, cell: Cell) {
+        let c = if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+            b' '
+        } else {
+            encode(cell.c)
+        };
+        let value = (self.attribute(&cell) as u16) << 8 | c as u16;
+        self.memory.write(row * self.width + col, value);
+    }
+
+    fn write_with_cursor(&mut self, row: usize, col: usize, cell: Cell, _shape: CursorShape) {
+        let mut cell = cell;
+        if self.ports.is_none() {
+            cell.flags.toggle(Flags::INVERSE);
+        }
+        self.write(row, col, cell);
+    }
+
+    fn set_cursor(&mut self, pos: Option<(usize, usize)>, shape: CursorShape) {
+        if self.cursor == Some((pos, shape)) {
+            return;
+        }
+        let shape_changed = self.cursor.map(|(_, shape)| shape) != Some(shape);
+        self.cursor = Some((pos, shape));
+        let width = self.width;
+        let ports = match self.ports.as_mut() {
+            Some(ports) => ports,
+            None => return,
+        };
+        let start = read_crtc(ports, CRTC_CURSOR_START);
+        let (row, col) = match pos {
+            Some(pos) => pos,
+            None => {
+                write_crtc(ports, CRTC_CURSOR_START, start | CURSOR_DISABLE);
+                return;
+            }
+        };
+        if shape_changed || start & CURSOR_DISABLE != 0 {
+            let max_scan_line = read_crtc(ports, CRTC_MAX_SCAN_LINE) & 0x1f;
+            let end = read_crtc(ports, CRTC_CURSOR_END);
+            let (first, last) = match shape {
+                CursorShape::Block => (0, max_scan_line),
+                CursorShape::Underline | CursorShape::Bar => (
+                    max_scan_line.saturating_sub(2),
+                    max_scan_line.saturating_sub(1),
+                ),
+            };
+            write_crtc(ports, CRTC_CURSOR_START, start & 0xc0 | first);
+            write_crtc(ports, CRTC_CURSOR_END, end & 0xe0 | last);
+        }
+        let index = (row * width + col) as u16;
+        write_crtc(ports, CRTC_CURSOR_HIGH, (index >> 8) as u8);
+        write_crtc(ports, CRTC_CURSOR_LOW, index as u8);
+    }
+}
+
+fn read_crtc(ports: &mut impl VgaPorts, index: u8) -> u8 {
+    ports.write_port(CRTC_INDEX, index);
+    ports.read_port(CRTC_DATA)
+}
+
+fn write_crtc(ports: &mut impl VgaPorts, index: u8, value: u8) {
+    ports.write_port(CRTC_INDEX, index);
+    ports.write_port(CRTC_DATA, value);
+}
+
+/// Convert between ANSI and VGA color indices, which differ in the order of red and blue bits
+fn swap_red_blue(color: u8) -> u8 {
+    color & 0b1010 | (color & 0b0001) << 2 | (color & 0b0100) >> 2
+}
+
+/// VGA color index nearest to `color`
+fn vga_color(color: Color) -> u8 {
+    match color {
+        Color::Named(name) => swap_red_blue(name as u8),
+        Color::Indexed(index) if index < 16 => swap_red_blue(index),
+        _ => {
+            let rgb = color.to_rgb();
+            let distance = |&(r, g, b): &(u8, u8, u8)| {
+                let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+                d(r, rgb.r()) + d(g, rgb.g()) + d(b, rgb.b())
+            };
+            (0..16u8)
+                .min_by_key(|&i| distance(&PALETTE[i as usize]))
+                .unwrap()
+        }
+    }
+}
+
+/// Code page 437 byte of `c`
+fn encode(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '⌂' => 0x7f,
+        _ => CP437_LOW
+            .chars()
+            .position(|x| x == c)
+            .or_else(|| CP437_HIGH.chars().position(|x| x == c).map(|i| i + 0x80))
+            .map_or(b'?', |i| i as u8),
+    }
+}
+
+/// Character of code page 437 byte `b`
+fn decode(b: u8) -> char {
+    match b {
+        0x20..=0x7e => b as char,
+        0x7f => '⌂',
+        0x00..=0x1f => CP437_LOW.chars().nth(b as usize).unwrap(),
+        _ => CP437_HIGH.chars().nth(b as usize - 0x80).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::NamedColor;
+    use alloc::vec::Vec;
+    use embedded_graphics::pixelcolor::Rgb888;
+
+    /// Text memory of 80x25 cells
+    struct Memory(Vec<u16>);
+
+    impl VgaMemory for Memory {
+        fn read(&self, index: usize) -> u16 {
+            self.0[index]
+        }
+
+        fn write(&mut self, index: usize, value: u16) {
+            self.0[index] = value;
+        }
+    }
+
+    /// CRT controller and attribute mode registers, recording the values written to them
+    #[derive(Default)]
+    struct Ports {
+        crtc: [u8; 0x20],
+        crtc_index: u8,
+        crtc_writes: Vec<(u8, u8)>,
+        attr_mode: u8,
+        attr_writes: Vec<u8>,
+    }
+
+    impl VgaPorts for Ports {
+        fn read_port(&mut self, port: u16) -> u8 {
+            match port {
+                CRTC_DATA => self.crtc[self.crtc_index as usize],
+                ATTR_DATA_READ => self.attr_mode,
+                _ => 0,
+            }
+        }
+
+        fn write_port(&mut self, port: u16, value: u8) {
+            match port {
+                CRTC_INDEX => self.crtc_index = value,
+                CRTC_DATA => {
+                    self.crtc[self.crtc_index as usize] = value;
+                    self.crtc_writes.push((self.crtc_index, value));
+                }
+                ATTR_INDEX => self.attr_writes.push(value),
+                _ => panic!("unexpected write to port {:#x}", port),
+            }
+        }
+    }
+
+    /// Buffer of 80x25 cells without port I/O
+    fn buffer() -> VgaTextBuffer<Memory> {
+        VgaTextBuffer::new(Memory(vec![0; 80 * 25]), 80, 25)
+    }
+
+    /// Blank cell with colors and `flags`
+    fn cell(fg: Color, bg: Color, flags: Flags) -> Cell {
+        let mut cell = Cell::default();
+        cell.fg = fg;
+        cell.bg = bg;
+        cell.flags = flags;
+        cell
+    }
+
+    #[test]
+    fn attribute() {
+        let mut buffer = buffer();
+        let red = Color::Named(NamedColor::Red);
+        let blue = Color::Named(NamedColor::Blue);
+        let attribute =
+            |buffer: &VgaTextBuffer<Memory>, flags| buffer.attribute(&cell(red, blue, flags));
+        assert_eq!(attribute(&buffer, Flags::empty()), 0x14);
+        assert_eq!(attribute(&buffer, Flags::BOLD), 0x1c);
+        assert_eq!(attribute(&buffer, Flags::INVERSE), 0x41);
+        assert_eq!(attribute(&buffer, Flags::BOLD | Flags::INVERSE), 0x41);
+        assert_eq!(attribute(&buffer, Flags::HIDDEN), 0x11);
+        assert_eq!(attribute(&buffer, Flags::BLINK), 0x94);
+
+        buffer.set_blink(false);
+        assert_eq!(attribute(&buffer, Flags::empty()), 0x14);
+        assert_eq!(attribute(&buffer, Flags::BOLD | Flags::INVERSE), 0xc1);
+        assert_eq!(attribute(&buffer, Flags::BLINK), 0x14);
+
+        // indexed and truecolor colors, with a bright background
+        let bright_red = Color::Indexed(9);
+        let bright_blue = Color::Indexed(12);
+        let colors = cell(bright_red, bright_blue, Flags::empty());
+        assert_eq!(buffer.attribute(&colors), 0x9c);
+        buffer.set_blink(true);
+        assert_eq!(buffer.attribute(&colors), 0x1c);
+        let spec = cell(
+            Color::Spec(Rgb888::new(250, 80, 80)),
+            Color::Spec(Rgb888::new(0, 0, 160)),
+            Flags::empty(),
+        );
+        assert_eq!(buffer.attribute(&spec), 0x1c);
+        // pure red of the 256 colors is nearer to red than to bright red
+        assert_eq!(
+            buffer.attribute(&cell(Color::Indexed(196), blue, Flags::empty())),
+            0x14
+        );
+    }
+
+    #[test]
+    fn code_page_437() {
+        for b in 1..=0xffu8 {
+            assert_eq!(encode(decode(b)), b, "{:#x}", b);
+        }
+        assert_eq!(decode(0), ' ');
+        assert_eq!(encode('é'), 0x82);
+        assert_eq!(encode('中'), b'?');
+    }
+
+    #[test]
+    fn write_and_read() {
+        let mut buffer = buffer();
+        let mut written = cell(
+            Color::Named(NamedColor::Red),
+            Color::Named(NamedColor::Blue),
+            Flags::BLINK,
+        );
+        written.c = 'é';
+        buffer.write(1, 2, written);
+        assert_eq!(buffer.memory.0[82], 0x9482);
+        let read = buffer.read(1, 2);
+        assert_eq!(read.c, 'é');
+        assert_eq!(read.fg, Color::Indexed(1));
+        assert_eq!(read.bg, Color::Indexed(4));
+        assert!(read.flags.contains(Flags::BLINK));
+    }
+
+    #[test]
+    fn set_blink() {
+        let mut buffer = buffer().with_ports(Ports {
+            attr_mode: 0x0c,
+            ..Ports::default()
+        });
+        buffer.set_blink(false);
+        buffer.set_blink(true);
+        let ports = buffer.ports.unwrap();
+        assert_eq!(
+            ports.attr_writes,
+            [ATTR_MODE_CONTROL, 0x04, ATTR_MODE_CONTROL, 0x0c]
+        );
+    }
+
+    #[test]
+    fn set_cursor() {
+        let mut crtc = [0; 0x20];
+        crtc[CRTC_MAX_SCAN_LINE as usize] = 0x4f;
+        crtc[CRTC_CURSOR_START as usize] = 0x0e;
+        crtc[CRTC_CURSOR_END as usize] = 0x0f;
+        let mut buffer = buffer().with_ports(Ports {
+            crtc,
+            ..Ports::default()
+        });
+        let writes = |buffer: &mut VgaTextBuffer<Memory, Ports>, pos, shape| {
+            buffer.set_cursor(pos, shape);
+            core::mem::take(&mut buffer.ports.as_mut().unwrap().crtc_writes)
+        };
+        let (start, end) = (CRTC_CURSOR_START, CRTC_CURSOR_END);
+        let (high, low) = (CRTC_CURSOR_HIGH, CRTC_CURSOR_LOW);
+
+        let block = CursorShape::Block;
+        assert_eq!(
+            writes(&mut buffer, Some((1, 2)), block),
+            [(start, 0), (end, 15), (high, 0), (low, 82)]
+        );
+        assert_eq!(writes(&mut buffer, Some((1, 2)), block), []);
+        assert_eq!(
+            writes(&mut buffer, Some((4, 3)), block),
+            [(high, 1), (low, 0x43)]
+        );
+
+        let underline = CursorShape::Underline;
+        assert_eq!(
+            writes(&mut buffer, Some((4, 3)), underline),
+            [(start, 13), (end, 14), (high, 1), (low, 0x43)]
+        );
+        assert_eq!(writes(&mut buffer, None, underline), [(start, 0x2d)]);
+        assert_eq!(
+            writes(&mut buffer, Some((0, 0)), underline),
+            [(start, 13), (end, 14), (high, 0), (low, 0)]
+        );
+    }
+}